@@ -1,9 +1,16 @@
-
 fn main() {
-    {
+    println!("cargo:rerun-if-env-changed=SAPNWRFC_HOME");
+    if let Ok(home) = std::env::var("SAPNWRFC_HOME") {
+        println!("cargo:rustc-flags=-L {}/lib", home);
+    } else {
         let path = env!("CARGO_MANIFEST_DIR");
-        if cfg!(target_vendor = "apple") {
+        // cfg! would describe the host, not the target when cross compiling
+        let target_vendor = std::env::var("CARGO_CFG_TARGET_VENDOR").unwrap_or_default();
+        let target_family = std::env::var("CARGO_CFG_TARGET_FAMILY").unwrap_or_default();
+        if target_vendor == "apple" {
             println!("cargo:rustc-flags=-L {}/saprfc/osx/nwrfcsdk/lib", path);
+        } else if target_family == "windows" {
+            println!("cargo:rustc-flags=-L {}/saprfc/windows/nwrfcsdk/lib", path);
         } else {
             println!("cargo:rustc-flags=-L {}/saprfc/gnulinux/nwrfcsdk/lib", path);
         }
@@ -0,0 +1,93 @@
+use std::sync::mpsc;
+
+use crate::error::*;
+use crate::rfc::*;
+use crate::{with_codepage_context, RfcFunction, RfcLib};
+
+/// What the blocking task needs to invoke the function
+struct InvokeJob {
+    rfc_lib: *const RfcLib,
+    connection: *mut RfcConnectionHandle,
+    fun: *mut RfcDataContainerHandle,
+}
+
+// SAFETY: the pointers come from an RfcFunction that is mutably borrowed
+// until the job has finished, see CompletionGuard and the contract of
+// call_async.
+unsafe impl Send for InvokeJob {}
+
+/// Blocks on drop until the blocking task has finished. Should the future
+/// of call_async be dropped early, this keeps the function and connection
+/// alive while the RFC library still uses them.
+struct CompletionGuard(mpsc::Receiver<()>);
+
+impl Drop for CompletionGuard {
+    fn drop(&mut self) {
+        // Returns once the task dropped its sender
+        let _ = self.0.recv();
+    }
+}
+
+impl<'conn, 'fun> RfcFunction<'conn, 'fun> {
+    /// Call the remote function on tokio's blocking thread pool, so the
+    /// RFC round trip does not stall the async runtime. Requires the `tokio`
+    /// feature.
+    ///
+    /// Functions and connections cannot be sent to other threads while they
+    /// are borrowed, so the returned future is not Send: run it on a
+    /// LocalSet or a current thread runtime, or give every task its own
+    /// connection. Dropping the future before it completes blocks until the
+    /// call has finished.
+    ///
+    /// # Safety
+    ///
+    /// The RFC library uses the function and connection handles on another
+    /// thread until the call has finished, which only the drop of the future
+    /// waits for. The caller must make sure that
+    ///
+    /// * the future is polled to completion or dropped, never leaked, e.g.
+    ///   with std::mem::forget, after it was polled for the first time, and
+    /// * no other function is called, and the connection is not reconnected,
+    ///   reset or closed, on the same connection while the future exists.
+    ///
+    /// ```no_run
+    /// # use rsrfc::*;
+    /// # fn example(conn: &RfcConnection) -> Result<(), rsrfc::error::RfcErrorInfo> {
+    /// let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let local = tokio::task::LocalSet::new();
+    /// local.block_on(&rt, async {
+    ///     let mut fun = conn.get_function("RFC_PING")?;
+    ///     // SAFETY: the future is awaited to completion, and nothing else
+    ///     // uses the connection meanwhile
+    ///     unsafe { fun.call_async() }.await
+    /// })
+    /// # }
+    /// ```
+    pub async unsafe fn call_async(&mut self) -> Result<(), RfcErrorInfo> {
+        let connection = self.connection()?;
+        let job = InvokeJob {
+            rfc_lib: self.rfc_lib,
+            connection: connection.connection_handle,
+            fun: self.fun,
+        };
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let task = tokio::task::spawn_blocking(move || {
+            let job = job;
+            let mut err_trunk = RfcErrorInfo::new();
+            let res = unsafe { (*job.rfc_lib).rfc_api.RfcInvoke(job.connection, job.fun, &mut err_trunk) };
+            drop(done_tx);
+            (res, err_trunk)
+        });
+        let guard = CompletionGuard(done_rx);
+        let res = task.await;
+        drop(guard);
+        self.forget_row_counts();
+        let (res, err_trunk) =
+            res.map_err(|e| RfcErrorInfo::custom(&format!("RFC call task failed: {}", e)))?;
+        if !res.is_ok() {
+            return Err(with_codepage_context(&connection.parms, err_trunk));
+        }
+        self.call_state.called.set(true);
+        Ok(())
+    }
+}
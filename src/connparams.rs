@@ -1,9 +1,12 @@
 use widestring::*;
 
+use crate::error::{interior_nul, RfcErrorInfo};
 use crate::rfc::*;
+use crate::{RfcConnection, RfcLib};
 
 /// Simple structure that supplies arbitrary key,value
 /// pairs to the SAP RFC library
+#[derive(Clone)]
 pub struct RfcConnParmHelper {
     parms: Vec<(Vec<u16>, Vec<u16>)>,
 }
@@ -14,11 +17,45 @@ impl RfcConnParmHelper {
         RfcConnParmHelper { parms: Vec::new() }
     }
 
-    /// Add a key,value pair
-    pub fn add(&mut self, k: &str, v: &str) {
-        let k_c = U16CString::from_str(k).unwrap().into_vec_with_nul();
-        let v_c = U16CString::from_str(v).unwrap().into_vec_with_nul();
-        self.parms.push((k_c, v_c));
+    /// Add a key,value pair. Fails if the key or the value contains a NUL
+    /// character, which the RFC library cannot represent.
+    pub fn add(&mut self, k: &str, v: &str) -> Result<(), RfcErrorInfo> {
+        let k_c = U16CString::from_str(k).map_err(|e| interior_nul("Connection parameter name", e))?;
+        let v_c = U16CString::from_str(v).map_err(|e| interior_nul(&format!("Value of connection parameter {}", k), e))?;
+        self.parms.push((k_c.into_vec_with_nul(), v_c.into_vec_with_nul()));
+        Ok(())
+    }
+
+    /// Return the value of a parameter, if it was set. Keys are compared
+    /// case insensitively, like the RFC library does.
+    pub fn get(&self, k: &str) -> Option<String> {
+        self.parms
+            .iter()
+            .rev()
+            .find(|(key, _)| decode_with_nul(key).eq_ignore_ascii_case(k))
+            .map(|(_, value)| decode_with_nul(value))
+    }
+
+    /// Check for parameter combinations that the RFC library would only
+    /// reject with an opaque logon failure. Direct connections (ashost,
+    /// sysnr) and load balanced connections (mshost, group) are mutually
+    /// exclusive, and SNC needs a partner name once it is switched on.
+    pub fn validate(&self) -> Result<(), RfcErrorInfo> {
+        let direct = self.get("ashost").is_some() || self.get("sysnr").is_some();
+        let balanced = self.get("mshost").is_some() || self.get("group").is_some();
+        if direct && balanced {
+            return Err(RfcErrorInfo::custom(
+                "ashost/sysnr and mshost/group are mutually exclusive; \
+                 use either a direct or a load balanced connection",
+            ));
+        }
+        let snc_on = self.get("snc_mode").is_some_and(|m| m.trim() == "1");
+        if snc_on && self.get("snc_partnername").is_none() {
+            return Err(RfcErrorInfo::custom(
+                "snc_mode is on but snc_partnername is not set",
+            ));
+        }
+        Ok(())
     }
 
     pub fn as_vec<F, T>(&self, mut f: F) -> T
@@ -36,3 +73,201 @@ impl RfcConnParmHelper {
         f(pp)
     }
 }
+
+/// Secure Network Communication (SNC) settings, see ConnectionBuilder::snc.
+/// The partner name is mandatory when SNC is switched on.
+#[derive(Default)]
+pub struct SncConfig<'a> {
+    /// Switch SNC on
+    pub mode: bool,
+    /// Quality of protection: 1 (authentication), 2 (integrity),
+    /// 3 (privacy), 8 (default) or 9 (maximum)
+    pub qop: Option<&'a str>,
+    /// SNC name of the SAP system, e.g. "p:CN=ABC, O=Example, C=DE"
+    pub partnername: Option<&'a str>,
+    /// Own SNC name, if not the default of the SNC library
+    pub myname: Option<&'a str>,
+    /// Path to the SNC library
+    pub lib: Option<&'a str>,
+}
+
+/// Decode a NUL terminated parameter key or value
+fn decode_with_nul(v: &[u16]) -> String {
+    let end = v.iter().position(|&c| c == 0).unwrap_or(v.len());
+    String::from_utf16_lossy(&v[..end])
+}
+
+/// Typed builder for RFC connection parameters. Each method sets one
+/// well-known parameter; use raw for anything not covered here.
+pub struct ConnectionBuilder {
+    parms: RfcConnParmHelper,
+    /// The first error of add, reported by build
+    error: Option<RfcErrorInfo>,
+}
+
+impl Default for ConnectionBuilder {
+    fn default() -> Self {
+        ConnectionBuilder::new()
+    }
+}
+
+impl ConnectionBuilder {
+    /// Create a builder without any parameters
+    pub fn new() -> ConnectionBuilder {
+        ConnectionBuilder {
+            parms: RfcConnParmHelper::new(),
+            error: None,
+        }
+    }
+
+    /// Host name of the application server
+    pub fn ashost(self, ashost: &str) -> ConnectionBuilder {
+        self.raw("ashost", ashost)
+    }
+
+    /// System number of the application server, e.g. "00"
+    pub fn sysnr(self, sysnr: &str) -> ConnectionBuilder {
+        self.raw("sysnr", sysnr)
+    }
+
+    /// Client (Mandant) to log on to, e.g. "001"
+    pub fn client(self, client: &str) -> ConnectionBuilder {
+        self.raw("client", client)
+    }
+
+    /// User name to log on with
+    pub fn user(self, user: &str) -> ConnectionBuilder {
+        self.raw("user", user)
+    }
+
+    /// Password to log on with
+    pub fn passwd(self, passwd: &str) -> ConnectionBuilder {
+        self.raw("passwd", passwd)
+    }
+
+    /// Logon language, e.g. "EN"
+    pub fn lang(self, lang: &str) -> ConnectionBuilder {
+        self.raw("lang", lang)
+    }
+
+    /// Host name of the message server, for load balanced connections
+    pub fn mshost(self, mshost: &str) -> ConnectionBuilder {
+        self.raw("mshost", mshost)
+    }
+
+    /// Service name or port of the message server
+    pub fn msserv(self, msserv: &str) -> ConnectionBuilder {
+        self.raw("msserv", msserv)
+    }
+
+    /// System ID of the SAP system, for load balanced connections
+    pub fn r3name(self, r3name: &str) -> ConnectionBuilder {
+        self.raw("r3name", r3name)
+    }
+
+    /// Logon group, for load balanced connections
+    pub fn group(self, group: &str) -> ConnectionBuilder {
+        self.raw("group", group)
+    }
+
+    /// SAP router string, e.g. "/H/saprouter.example.com/S/3299/H/"
+    pub fn saprouter(self, saprouter: &str) -> ConnectionBuilder {
+        self.raw("saprouter", saprouter)
+    }
+
+    /// SAP codepage number of the connection, e.g. "1100" for a
+    /// non-Unicode system using Latin-1. Anything but a four digit number
+    /// is reported by build.
+    pub fn codepage(mut self, codepage: &str) -> ConnectionBuilder {
+        if codepage.len() != 4 || !codepage.bytes().all(|b| b.is_ascii_digit()) {
+            self.error.get_or_insert(RfcErrorInfo::custom(&format!(
+                "Invalid codepage {}: expected a four digit SAP codepage number such as 1100",
+                codepage
+            )));
+            return self;
+        }
+        self.raw("codepage", codepage)
+    }
+
+    /// Program ID an RFC server registers with at the gateway
+    pub fn program_id(self, program_id: &str) -> ConnectionBuilder {
+        self.raw("program_id", program_id)
+    }
+
+    /// Host name of the SAP gateway, for RFC servers
+    pub fn gwhost(self, gwhost: &str) -> ConnectionBuilder {
+        self.raw("gwhost", gwhost)
+    }
+
+    /// Service name or port of the SAP gateway, e.g. "sapgw00"
+    pub fn gwserv(self, gwserv: &str) -> ConnectionBuilder {
+        self.raw("gwserv", gwserv)
+    }
+
+    /// RFC trace level, 0 (off) to 3 (full)
+    pub fn trace(self, level: u8) -> ConnectionBuilder {
+        self.raw("trace", &level.to_string())
+    }
+
+    /// Secure Network Communication settings
+    pub fn snc(self, snc: SncConfig) -> ConnectionBuilder {
+        let mut builder = self.raw("snc_mode", if snc.mode { "1" } else { "0" });
+        if let Some(qop) = snc.qop {
+            builder = builder.raw("snc_qop", qop);
+        }
+        if let Some(partnername) = snc.partnername {
+            builder = builder.raw("snc_partnername", partnername);
+        }
+        if let Some(myname) = snc.myname {
+            builder = builder.raw("snc_myname", myname);
+        }
+        if let Some(lib) = snc.lib {
+            builder = builder.raw("snc_lib", lib);
+        }
+        builder
+    }
+
+    /// Set an arbitrary connection parameter
+    pub fn raw(mut self, key: &str, value: &str) -> ConnectionBuilder {
+        if let Err(e) = self.parms.add(key, value) {
+            self.error.get_or_insert(e);
+        }
+        self
+    }
+
+    /// Return the collected parameters, or the error of the first
+    /// parameter that could not be added
+    pub fn build(self) -> Result<RfcConnParmHelper, RfcErrorInfo> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.parms),
+        }
+    }
+
+    /// Open a connection using the collected parameters
+    pub fn connect(self, rfc_lib: &RfcLib) -> Result<RfcConnection<'_>, RfcErrorInfo> {
+        RfcConnection::from_parm_helper(self.build()?, rfc_lib)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codepage_must_be_a_number() {
+        let parms = ConnectionBuilder::new().codepage("1100").build().unwrap();
+        assert_eq!(parms.get("codepage").as_deref(), Some("1100"));
+
+        for codepage in ["latin1", "110", "11000", ""] {
+            let err = match ConnectionBuilder::new().ashost("host").codepage(codepage).build() {
+                Ok(_) => panic!("codepage {:?} was accepted", codepage),
+                Err(e) => e,
+            };
+            assert_eq!(
+                err.message(),
+                format!("Invalid codepage {}: expected a four digit SAP codepage number such as 1100", codepage)
+            );
+        }
+    }
+}
@@ -59,6 +59,7 @@ pub enum RfcErrorGroup {
 }
 
 #[repr(C)]
+#[derive(Clone)]
 pub struct RfcErrorInfo {
     pub code: RfcRc,
     pub group: RfcErrorGroup,
@@ -73,6 +74,178 @@ pub struct RfcErrorInfo {
     pub abap_msg_v4: [u16; 51],
 }
 
+impl RfcErrorInfo {
+    /// Return the RFC return code of this error
+    pub fn code(&self) -> RfcRc {
+        self.code
+    }
+
+    /// Language independent key of the error
+    pub fn key(&self) -> String {
+        decode_buf(&self.key)
+    }
+
+    /// Return true if the key of the error is `key`, ignoring case. Unlike
+    /// the message, the key does not depend on the logon language.
+    pub fn matches_key(&self, key: &str) -> bool {
+        self.key().eq_ignore_ascii_case(key)
+    }
+
+    /// Error message text
+    pub fn message(&self) -> String {
+        decode_buf(&self.message)
+    }
+
+    /// Message class of an ABAP message
+    pub fn abap_message_class(&self) -> String {
+        decode_buf(&self.abap_msg_class)
+    }
+
+    /// One-character type code of an ABAP message, e.g. E or W
+    pub fn abap_message_type_code(&self) -> String {
+        decode_buf(&self.abap_msg_type)
+    }
+
+    /// Type of an ABAP message, or None if the error carries no message
+    /// or an unknown type
+    pub fn abap_message_type(&self) -> Option<AbapMessageType> {
+        match self.abap_message_type_code().as_str() {
+            "E" => Some(AbapMessageType::Error),
+            "A" => Some(AbapMessageType::Abort),
+            "X" => Some(AbapMessageType::Exit),
+            "W" => Some(AbapMessageType::Warning),
+            "I" => Some(AbapMessageType::Info),
+            "S" => Some(AbapMessageType::Success),
+            _ => None,
+        }
+    }
+
+    /// Number of an ABAP message within its message class
+    pub fn abap_message_number(&self) -> String {
+        decode_buf(&self.abap_msg_number)
+    }
+
+    /// First variable of an ABAP message
+    pub fn abap_message_v1(&self) -> String {
+        decode_buf(&self.abap_msg_v1)
+    }
+
+    /// Second variable of an ABAP message
+    pub fn abap_message_v2(&self) -> String {
+        decode_buf(&self.abap_msg_v2)
+    }
+
+    /// Third variable of an ABAP message
+    pub fn abap_message_v3(&self) -> String {
+        decode_buf(&self.abap_msg_v3)
+    }
+
+    /// Fourth variable of an ABAP message
+    pub fn abap_message_v4(&self) -> String {
+        decode_buf(&self.abap_msg_v4)
+    }
+
+    /// Return true if the error happened in the network or communication
+    /// layer. The connection is usually unusable afterwards.
+    pub fn is_communication_failure(&self) -> bool {
+        self.code == RfcRc::RfcCommunicationFailure
+    }
+
+    /// Return true if logging on to the SAP system failed, e.g. because
+    /// of an invalid password or a locked user.
+    pub fn is_logon_failure(&self) -> bool {
+        self.code == RfcRc::RfcLogonFailure
+    }
+
+    /// Return true if the called function module raised a classic
+    /// (RAISE or MESSAGE ... RAISING) or class based exception.
+    pub fn is_abap_exception(&self) -> bool {
+        self.code == RfcRc::RfcAbapException || self.code == RfcRc::RfcAbapClassException
+    }
+
+    /// Sort the error into one of the broad categories of RfcError
+    pub fn classify(&self) -> RfcError {
+        match self.code {
+            RfcRc::RfcCommunicationFailure | RfcRc::RfcClosed => RfcError::Communication,
+            RfcRc::RfcLogonFailure => RfcError::Logon,
+            RfcRc::RfcAbapException | RfcRc::RfcAbapClassException | RfcRc::RfcAbapMessage => {
+                RfcError::AbapException
+            }
+            RfcRc::RfcAbapRuntimeFailure => RfcError::AbapRuntime,
+            RfcRc::RfcSerializationFailure
+            | RfcRc::RfcInvalidProtocol
+            | RfcRc::RfcConversionFailure
+            | RfcRc::RfcCodepageConversionFailure => RfcError::Serialization,
+            RfcRc::RfcTimeout => RfcError::Timeout,
+            _ => RfcError::Other(Box::new(self.clone())),
+        }
+    }
+}
+
+/// Broad categories of RFC errors, see RfcErrorInfo::classify
+#[derive(Debug, Clone)]
+pub enum RfcError {
+    /// The network connection failed or was closed by the other side
+    Communication,
+    /// Logging on failed, e.g. because of a wrong password or a locked user
+    Logon,
+    /// The function module raised an exception or an E, A or X message
+    AbapException,
+    /// The SAP system aborted with a runtime error (short dump)
+    AbapRuntime,
+    /// Data could not be converted or had an unsupported format
+    Serialization,
+    /// The operation timed out
+    Timeout,
+    /// Any other error. Boxed, as RfcErrorInfo is large.
+    Other(Box<RfcErrorInfo>),
+}
+
+/// Type of an ABAP message, see RfcErrorInfo::abap_message_type
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AbapMessageType {
+    /// E: error
+    Error,
+    /// A: termination
+    Abort,
+    /// X: exit with a short dump
+    Exit,
+    /// W: warning
+    Warning,
+    /// I: information
+    Info,
+    /// S: status or success message
+    Success,
+}
+
+/// Decode a NUL terminated UTF-16 buffer, replacing invalid code units
+pub(crate) fn decode_buf(buf: &[u16]) -> String {
+    let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..end]).trim().to_string()
+}
+
+/// Decode a NUL terminated UTF-16 name or value from a buffer of the RFC
+/// library. A buffer of NULs decodes to an empty string.
+pub(crate) fn decode_name(buf: &[u16], what: &str) -> Result<String, RfcErrorInfo> {
+    let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16(&buf[..end]).map_err(|e| invalid_utf16(what, e))
+}
+
+/// Error for a value of the RFC library that is not valid UTF-16
+pub(crate) fn invalid_utf16(what: &str, e: impl std::fmt::Display) -> RfcErrorInfo {
+    let mut err = RfcErrorInfo::custom(&format!("Invalid UTF-16 in {}: {}", what, e));
+    err.code = RfcRc::RfcConversionFailure;
+    err
+}
+
+/// Error for a value that cannot be passed to the RFC library, because it
+/// contains a NUL character
+pub(crate) fn interior_nul(what: &str, e: impl std::fmt::Display) -> RfcErrorInfo {
+    let mut err = RfcErrorInfo::custom(&format!("{} contains a NUL character: {}", what, e));
+    err.code = RfcRc::RfcInvalidParameter;
+    err
+}
+
 impl std::fmt::Debug for RfcErrorInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let msg = String::from_utf16_lossy(&self.message);
@@ -89,4 +262,42 @@ impl std::fmt::Display for RfcErrorInfo {
     }
 }
 
+/// Errors are equal if their code, group and key match. The message is
+/// ignored, as it depends on the logon language.
+impl PartialEq for RfcErrorInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.group == other.group && self.key() == other.key()
+    }
+}
+
 impl std::error::Error for RfcErrorInfo {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_buf(name: &str) -> [u16; 31] {
+        let mut buf = [0u16; 31];
+        for (i, c) in name.encode_utf16().enumerate() {
+            buf[i] = c;
+        }
+        buf
+    }
+
+    #[test]
+    fn decode_name_stops_at_nul() {
+        assert_eq!(decode_name(&name_buf("BAPI_USER_GET_DETAIL"), "name").unwrap(), "BAPI_USER_GET_DETAIL");
+        assert_eq!(decode_name(&name_buf(""), "name").unwrap(), "");
+        let full: Vec<u16> = "ABC".encode_utf16().collect();
+        assert_eq!(decode_name(&full, "name").unwrap(), "ABC");
+    }
+
+    #[test]
+    fn decode_name_rejects_invalid_utf16() {
+        let err = decode_name(&[0x41, 0xD800, 0x42, 0], "parameter name").unwrap_err();
+        assert_eq!(err.code, RfcRc::RfcConversionFailure);
+        assert!(err.message().starts_with("Invalid UTF-16 in parameter name"));
+        // decode_buf replaces instead
+        assert_eq!(decode_buf(&[0x41, 0xD800, 0x42, 0]), "A\u{FFFD}B");
+    }
+}
@@ -0,0 +1,517 @@
+use serde::de::value::{SeqDeserializer, StringDeserializer};
+use serde::de::{DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{Map, Number, Value};
+
+use crate::error::*;
+use crate::rfc::*;
+use crate::RfcFunction;
+
+impl<'conn, 'fun> RfcFunction<'conn, 'fun> {
+    /// Read all readable parameters (EXPORTING, CHANGING and TABLES) into a
+    /// JSON object keyed by parameter name. Structures become objects,
+    /// tables become arrays of objects. Requires the `serde` feature.
+    pub fn to_json(&self) -> Result<Value, RfcErrorInfo> {
+        let mut res = Map::new();
+        for p in self.fun_desc.iter().filter(|p| p.direction.can_read()) {
+            res.insert(p.name.clone(), parameter_to_json(p)?);
+        }
+        Ok(Value::Object(res))
+    }
+
+    /// Set import parameters from a JSON object keyed by parameter name.
+    /// Keys are matched case insensitively. Scalars are converted according
+    /// to the parameter type, objects fill structures and arrays of objects
+    /// are appended as table rows. Null values are skipped. Keys that match
+    /// no parameter or field are collected and reported together before
+    /// anything is set. Requires the `serde` feature.
+    pub fn apply_json(&mut self, value: &Value) -> Result<(), RfcErrorInfo> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| RfcErrorInfo::custom("Expected a JSON object of parameters"))?;
+        let mut unknown = Vec::new();
+        for (key, v) in obj {
+            match self.get_mut_parameter(key) {
+                Some(p) => collect_unknown(p, v, key, &mut unknown),
+                None => unknown.push(key.clone()),
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Unknown parameters or fields: {}",
+                unknown.join(", ")
+            )));
+        }
+        for (key, v) in obj {
+            if let Some(p) = self.get_mut_parameter(key) {
+                apply_to_parameter(p, v, key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
+    /// Read a structure or table parameter into a user type implementing
+    /// `Deserialize`. Field names are the ABAP names, so Rust structs usually
+    /// need `#[serde(rename_all = "UPPERCASE")]`. Nested structures map to
+    /// nested structs and tables to `Vec<T>`.
+    ///
+    /// Every field is read with the getter of its type: integers and floats
+    /// as numbers, BYTE and XSTRING as bytes and CHAR, NUM and STRING as
+    /// strings. A CHAR field read into a `bool` is true for "X". Packed and
+    /// decimal float fields are read as strings, so they fit `Decimal` and
+    /// `String` without losing precision, or as numbers into `f64`. With the
+    /// `chrono` feature, DATE and TIME fields are read as ISO 8601 strings
+    /// that fit `NaiveDate` and `NaiveTime`, and an initial date reads as
+    /// None. Requires the `serde` feature.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, RfcErrorInfo> {
+        T::deserialize(ParameterDeserializer { p: self, fields: false }).map_err(|mut e| {
+            e.set_message(&format!("Cannot deserialize parameter {}: {}", self.name, e.message()));
+            e
+        })
+    }
+}
+
+impl serde::de::Error for RfcErrorInfo {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        RfcErrorInfo::custom(&msg.to_string())
+    }
+}
+
+/// Deserializer over one parameter or field. With `fields` set, it reads
+/// the fields of a structure, or of the current row of a table, as a map.
+struct ParameterDeserializer<'a, 'conn, 'strct: 'conn> {
+    p: &'a RfcParameter<'conn, 'strct>,
+    fields: bool,
+}
+
+impl<'de, 'a, 'conn, 'strct: 'conn> Deserializer<'de> for ParameterDeserializer<'a, 'conn, 'strct> {
+    type Error = RfcErrorInfo;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        let p = self.p;
+        if self.fields {
+            let struct_def = p.struct_def_or_err()?;
+            return visitor.visit_map(FieldsAccess { fields: struct_def.parameters.iter(), current: None });
+        }
+        match p.field_type {
+            RfcType::Structure => visitor.visit_map(FieldsAccess {
+                fields: p.struct_def_or_err()?.parameters.iter(),
+                current: None,
+            }),
+            RfcType::Table => visitor.visit_seq(RowsAccess { p, row: 0, count: p.get_row_count()? }),
+            RfcType::Int | RfcType::Int1 | RfcType::Int2 => visitor.visit_i64(p.get_int()?),
+            RfcType::Int8 => visitor.visit_i64(p.get_int8()?),
+            RfcType::Float => visitor.visit_f64(p.get_float()?),
+            #[cfg(feature = "decimal")]
+            RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => visitor.visit_string(p.get_decimal()?.to_string()),
+            #[cfg(not(feature = "decimal"))]
+            RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => visitor.visit_string(leading_sign(&p.get_string()?)),
+            #[cfg(feature = "chrono")]
+            RfcType::Date => match p.get_date_opt()? {
+                Some(date) => visitor.visit_string(date.to_string()),
+                None => visitor.visit_none(),
+            },
+            #[cfg(feature = "chrono")]
+            RfcType::Time => visitor.visit_string(p.get_time()?.to_string()),
+            #[cfg(not(feature = "chrono"))]
+            RfcType::Date | RfcType::Time => visitor.visit_string(p.get_chars_trimmed()?),
+            RfcType::Char => visitor.visit_string(p.get_chars_trimmed()?),
+            RfcType::Num => visitor.visit_string(p.get_num()?),
+            RfcType::String => visitor.visit_string(p.get_string()?),
+            RfcType::Byte => visitor.visit_seq(SeqDeserializer::new(p.get_bytes()?.into_iter())),
+            RfcType::XString => visitor.visit_seq(SeqDeserializer::new(p.get_xstring()?.into_iter())),
+            _ => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        match self.p.field_type {
+            RfcType::Char if !self.fields => visitor.visit_bool(self.p.get_chars_trimmed()? == "X"),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        match self.p.field_type {
+            RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 if !self.fields => {
+                let v = leading_sign(&self.p.get_string()?);
+                let f = v.parse().map_err(|_| {
+                    RfcErrorInfo::custom(&format!("Invalid decimal value {} in field {}", v, self.p.name))
+                })?;
+                visitor.visit_f64(f)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        match self.p.field_type {
+            RfcType::Byte if !self.fields => visitor.visit_byte_buf(self.p.get_bytes()?),
+            RfcType::XString if !self.fields => visitor.visit_byte_buf(self.p.get_xstring()?),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        #[cfg(feature = "chrono")]
+        if self.p.field_type == RfcType::Date && !self.fields && self.p.get_date_opt()?.is_none() {
+            return visitor.visit_none();
+        }
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RfcErrorInfo> {
+        // Skipped fields are not read at all
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+    }
+}
+
+/// The fields of a structure, or of the current row of a table, as a map
+/// from field name to value
+struct FieldsAccess<'a, 'conn, 'strct: 'conn> {
+    fields: std::slice::Iter<'a, RfcParameter<'conn, 'strct>>,
+    current: Option<&'a RfcParameter<'conn, 'strct>>,
+}
+
+impl<'de, 'a, 'conn, 'strct: 'conn> MapAccess<'de> for FieldsAccess<'a, 'conn, 'strct> {
+    type Error = RfcErrorInfo;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, RfcErrorInfo> {
+        self.current = self.fields.next();
+        match self.current {
+            Some(field) => {
+                let key: StringDeserializer<RfcErrorInfo> = field.name.clone().into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, RfcErrorInfo> {
+        let field = self
+            .current
+            .ok_or_else(|| RfcErrorInfo::custom("Field value requested before its name"))?;
+        seed.deserialize(ParameterDeserializer { p: field, fields: false })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+/// The rows of a table, each read as a map of its fields
+struct RowsAccess<'a, 'conn, 'strct: 'conn> {
+    p: &'a RfcParameter<'conn, 'strct>,
+    row: u32,
+    count: u32,
+}
+
+impl<'de, 'a, 'conn, 'strct: 'conn> SeqAccess<'de> for RowsAccess<'a, 'conn, 'strct> {
+    type Error = RfcErrorInfo;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, RfcErrorInfo> {
+        if self.row >= self.count {
+            return Ok(None);
+        }
+        self.p.set_row(self.row)?;
+        self.row += 1;
+        seed.deserialize(ParameterDeserializer { p: self.p, fields: true }).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.count - self.row) as usize)
+    }
+}
+
+/// Move the trailing minus the RFC library puts behind negative packed
+/// numbers to the front, e.g. "1234.56-" to "-1234.56"
+fn leading_sign(v: &str) -> String {
+    let v = v.trim();
+    match v.strip_suffix('-') {
+        Some(abs) => format!("-{}", abs.trim()),
+        None => v.to_string(),
+    }
+}
+
+/// Add the keys of `v` that match no field of the structure or table `p`
+/// to `unknown`, recursing into nested structures and tables
+fn collect_unknown(p: &RfcParameter, v: &Value, path: &str, unknown: &mut Vec<String>) {
+    let struct_def = match p.struct_def_or_err() {
+        Ok(struct_def) => struct_def,
+        Err(_) => return,
+    };
+    let objects: Vec<&Map<String, Value>> = match v {
+        Value::Object(obj) => vec![obj],
+        Value::Array(rows) => rows.iter().filter_map(Value::as_object).collect(),
+        _ => return,
+    };
+    for obj in objects {
+        for (key, v) in obj {
+            let field_path = format!("{}.{}", path, key);
+            match struct_def.parameters.iter().find(|f| f.name.eq_ignore_ascii_case(key)) {
+                Some(field) => collect_unknown(field, v, &field_path, unknown),
+                None if !unknown.contains(&field_path) => unknown.push(field_path),
+                None => {}
+            }
+        }
+    }
+}
+
+/// Set one parameter or field from a JSON value. `path` names the
+/// parameter in error messages.
+fn apply_to_parameter(p: &mut RfcParameter, v: &Value, path: &str) -> Result<(), RfcErrorInfo> {
+    if v.is_null() {
+        return Ok(());
+    }
+    if !p.direction.can_write() {
+        return Err(RfcErrorInfo::custom(&format!(
+            "Parameter {} is read-only",
+            path
+        )));
+    }
+    match p.field_type {
+        RfcType::Structure => apply_to_fields(p, expect_object(v, path)?, path),
+        RfcType::Table => {
+            let rows = v.as_array().ok_or_else(|| {
+                RfcErrorInfo::custom(&format!("Expected an array of rows for table {}", path))
+            })?;
+            for row in rows {
+                let row = expect_object(row, path)?;
+                p.append_rows(1)?;
+                p.last_row()?;
+                apply_to_fields(p, row, path)?;
+            }
+            Ok(())
+        }
+        RfcType::Char | RfcType::String => p.set_string(&scalar_to_string(v, path)?),
+        RfcType::Num => p.set_num(&scalar_to_string(v, path)?),
+        RfcType::Int | RfcType::Int1 | RfcType::Int2 => p.set_int(to_i64(v, path)?),
+        RfcType::Int8 => p.set_int8(to_i64(v, path)?),
+        RfcType::Float => p.set_float(to_f64(v, path)?),
+        RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => {
+            p.set_string_converted(&scalar_to_string(v, path)?)
+        }
+        // Accept 2023-01-31 and 12:30:00 as well as the ABAP formats
+        RfcType::Date | RfcType::Time => {
+            p.set_string_converted(&scalar_to_string(v, path)?.replace(['-', ':'], ""))
+        }
+        RfcType::Byte => p.set_bytes(&from_hex(&scalar_to_string(v, path)?, path)?),
+        RfcType::XString => p.set_xstring(&from_hex(&scalar_to_string(v, path)?, path)?),
+        other => Err(RfcErrorInfo::custom(&format!(
+            "Cannot set parameter {} of type {:?} from JSON",
+            path, other
+        ))),
+    }
+}
+
+/// Set the fields of a structure, or of the current row of a table, from
+/// a JSON object
+fn apply_to_fields(p: &mut RfcParameter, obj: &Map<String, Value>, path: &str) -> Result<(), RfcErrorInfo> {
+    for (key, v) in obj {
+        let field_path = format!("{}.{}", path, key);
+        apply_to_parameter(p.get_field_mut(key)?, v, &field_path)?;
+    }
+    Ok(())
+}
+
+fn expect_object<'a>(v: &'a Value, path: &str) -> Result<&'a Map<String, Value>, RfcErrorInfo> {
+    v.as_object()
+        .ok_or_else(|| RfcErrorInfo::custom(&format!("Expected an object for {}", path)))
+}
+
+/// Convert a scalar JSON value to its ABAP string representation. Booleans
+/// map to the ABAP convention of "X" and blank.
+fn scalar_to_string(v: &Value, path: &str) -> Result<String, RfcErrorInfo> {
+    match v {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(true) => Ok("X".to_string()),
+        Value::Bool(false) => Ok(String::new()),
+        _ => Err(RfcErrorInfo::custom(&format!(
+            "Expected a scalar value for {}",
+            path
+        ))),
+    }
+}
+
+fn to_i64(v: &Value, path: &str) -> Result<i64, RfcErrorInfo> {
+    match v {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| RfcErrorInfo::custom(&format!("Expected an integer for {}", path)))
+}
+
+fn to_f64(v: &Value, path: &str) -> Result<f64, RfcErrorInfo> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| RfcErrorInfo::custom(&format!("Expected a number for {}", path)))
+}
+
+/// Decode a hex string, as produced by to_hex
+fn from_hex(s: &str, path: &str) -> Result<Vec<u8>, RfcErrorInfo> {
+    let mut res = Vec::with_capacity(s.len() / 2);
+    for pair in s.as_bytes().chunks(2) {
+        match std::str::from_utf8(pair)
+            .ok()
+            .filter(|_| pair.len() == 2)
+            .and_then(|b| u8::from_str_radix(b, 16).ok())
+        {
+            Some(b) => res.push(b),
+            None => {
+                return Err(RfcErrorInfo::custom(&format!(
+                    "Expected a hex string for {}",
+                    path
+                )))
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Convert one parameter or field to the closest JSON representation
+fn parameter_to_json(p: &RfcParameter) -> Result<Value, RfcErrorInfo> {
+    let v = match p.field_type {
+        RfcType::Structure => fields_to_json(p)?,
+        RfcType::Table => {
+            let count = p.get_row_count()?;
+            let mut rows = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                p.set_row(i)?;
+                rows.push(fields_to_json(p)?);
+            }
+            Value::Array(rows)
+        }
+        RfcType::Int | RfcType::Int1 | RfcType::Int2 => Value::from(p.get_int()?),
+        RfcType::Int8 => Value::from(p.get_int8()?),
+        RfcType::Float => Number::from_f64(p.get_float()?).map_or(Value::Null, Value::Number),
+        RfcType::Char | RfcType::Date | RfcType::Time => Value::from(p.get_chars()?.trim_end()),
+        RfcType::Num => Value::from(p.get_num()?),
+        // Decimal numbers are kept as strings so no precision is lost
+        RfcType::String | RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => {
+            Value::from(p.get_string()?.trim_end())
+        }
+        RfcType::Byte => Value::from(to_hex(&p.get_bytes()?)),
+        RfcType::XString => Value::from(to_hex(&p.get_xstring()?)),
+        _ => Value::Null,
+    };
+    Ok(v)
+}
+
+/// Convert the fields of a structure, or of the current row of a table,
+/// to a JSON object
+fn fields_to_json(p: &RfcParameter) -> Result<Value, RfcErrorInfo> {
+    let struct_def = p.struct_def_or_err()?;
+    let mut res = Map::new();
+    for field in &struct_def.parameters {
+        res.insert(field.name.clone(), parameter_to_json(field)?);
+    }
+    Ok(Value::Object(res))
+}
+
+/// Hex encode binary data
+fn to_hex(v: &[u8]) -> String {
+    v.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{FieldSpec, ParameterSpec, RfcRepository};
+    use crate::RfcLib;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn leading_sign_moves_the_minus() {
+        assert_eq!(leading_sign("1234.56-"), "-1234.56");
+        assert_eq!(leading_sign(" 0.50 "), "0.50");
+        assert_eq!(leading_sign("-7"), "-7");
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn deserialize_reads_typed_fields() {
+        let lib = RfcLib::new().expect("libsapnwrfc not found");
+        let mut repository = RfcRepository::new(&lib, "RSRFC_TEST").unwrap();
+        let field = |name, field_type, nuc_length, uc_length| FieldSpec {
+            name,
+            field_type,
+            nuc_length,
+            uc_length,
+            decimals: 0,
+            type_name: None,
+        };
+        let fields = [
+            field("NAME", RfcType::Char, 10, 20),
+            field("COUNT", RfcType::Int, 4, 4),
+            field("RATE", RfcType::Float, 8, 8),
+            field("FLAG", RfcType::Char, 1, 2),
+        ];
+        repository.add_type("ZRSRFC_TYPED", &fields).unwrap();
+        let typed = ParameterSpec {
+            name: "TYPED",
+            field_type: RfcType::Structure,
+            direction: RfcDirection::RfcChanging,
+            nuc_length: 32,
+            uc_length: 40,
+            decimals: 0,
+            optional: false,
+            type_name: Some("ZRSRFC_TYPED"),
+        };
+        repository.add_function("Z_RSRFC_DESERIALIZE", &[typed]).unwrap();
+        let mut fun = repository.get_function("Z_RSRFC_DESERIALIZE").unwrap();
+        let p = fun.get_mut_parameter("TYPED").unwrap();
+        p.get_field_mut("NAME").unwrap().set_string("Müller").unwrap();
+        p.get_field_mut("COUNT").unwrap().set_int(42).unwrap();
+        p.get_field_mut("RATE").unwrap().set_float(1.5).unwrap();
+        p.get_field_mut("FLAG").unwrap().set_string("X").unwrap();
+
+        let v: HashMap<String, Value> = p.deserialize().unwrap();
+        assert_eq!(v["NAME"], json!("Müller"));
+        assert_eq!(v["COUNT"], json!(42));
+        assert_eq!(v["RATE"], json!(1.5));
+        assert_eq!(v["FLAG"], json!("X"));
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn apply_json_checks_all_keys_first() {
+        let lib = RfcLib::new().expect("libsapnwrfc not found");
+        let mut repository = RfcRepository::new(&lib, "RSRFC_TEST").unwrap();
+        let name = ParameterSpec {
+            name: "NAME",
+            field_type: RfcType::Char,
+            direction: RfcDirection::RfcImport,
+            nuc_length: 10,
+            uc_length: 20,
+            decimals: 0,
+            optional: false,
+            type_name: None,
+        };
+        repository.add_function("Z_RSRFC_APPLY_JSON", &[name]).unwrap();
+        let mut fun = repository.get_function("Z_RSRFC_APPLY_JSON").unwrap();
+        let err = fun.apply_json(&json!({"NAME": "Müller", "NOPE": 1})).unwrap_err();
+        assert_eq!(err.message(), "Unknown parameters or fields: NOPE");
+        assert!(!fun.get_mut_parameter("NAME").unwrap().was_written());
+    }
+}
@@ -1,19 +1,49 @@
+// RfcErrorInfo mirrors RFC_ERROR_INFO of the NW RFC library, which is
+// filled in place through a pointer, so it is returned by value throughout.
+#![allow(clippy::result_large_err)]
+
 extern crate dlopen;
 #[macro_use]
 extern crate dlopen_derive;
 extern crate widestring;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "decimal")]
+extern crate rust_decimal;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
 use widestring::*;
 
 pub mod connparams;
 pub mod error;
+pub mod metadata;
+pub mod pool;
+pub mod readtable;
+pub mod repository;
+pub mod server;
+pub mod transaction;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "tokio")]
+mod async_call;
 mod rfc;
 
 use crate::connparams::*;
 use crate::error::*;
+use crate::metadata::*;
 use crate::rfc::*;
 
+pub use crate::rfc::{RfcDecodedField, RfcDecodedFieldDesc, RfcDirection, RfcType, MAX_APPEND_ROWS};
+
 /// Simple RFC connections require only a few parameters.
 /// You can use this struct to supply them.
 pub struct RfcConnectionParameters<'a> {
@@ -25,51 +55,162 @@ pub struct RfcConnectionParameters<'a> {
     pub lang: &'a str,
 }
 
+/// Parameters for a load balanced connection via a message server.
+/// Load balanced connections (mshost, group) and direct connections to an
+/// application server (ashost, sysnr) are mutually exclusive.
+pub struct RfcLoadBalancedParameters<'a> {
+    pub mshost: &'a str,
+    pub msserv: &'a str,
+    pub group: &'a str,
+    pub r3name: &'a str,
+    pub client: &'a str,
+    pub user: &'a str,
+    pub passwd: &'a str,
+    pub lang: &'a str,
+}
+
+/// A value of an RFC parameter or field, see RfcParameter::set_value
+#[derive(Debug, Clone, PartialEq)]
 pub enum RfcValue {
+    /// A string, converted by the RFC library to the parameter type. Works
+    /// for STRING and CHAR as well as for numeric types like packed numbers.
     RfcString(String),
+    /// INT, INT1, INT2 or INT8
+    Int(i64),
+    /// FLOAT
+    Float(f64),
+    /// CHAR, NUMC, DATS (YYYYMMDD) or TIMS (HHMMSS) in their ABAP format
+    Chars(String),
+    /// RAW or XSTRING
+    Bytes(Vec<u8>),
+    #[cfg(feature = "chrono")]
+    Date(chrono::NaiveDate),
+    #[cfg(feature = "chrono")]
+    Time(chrono::NaiveTime),
 }
 
 impl<'a> RfcConnectionParameters<'a> {
     /// Convert to a more generic RfcConnParmHelper structure
-    fn convert(&self) -> RfcConnParmHelper {
+    fn convert(&self) -> Result<RfcConnParmHelper, RfcErrorInfo> {
         let mut parms = RfcConnParmHelper::new();
-        parms.add("ashost", &self.ashost);
-        parms.add("sysnr", &self.sysnr);
-        parms.add("client", &self.client);
-        parms.add("user", &self.user);
-        parms.add("passwd", &self.passwd);
-        parms.add("lang", &self.lang);
-        parms
+        parms.add("ashost", self.ashost)?;
+        parms.add("sysnr", self.sysnr)?;
+        parms.add("client", self.client)?;
+        parms.add("user", self.user)?;
+        parms.add("passwd", self.passwd)?;
+        parms.add("lang", self.lang)?;
+        Ok(parms)
     }
 }
 
-/// An open RFC connection
+impl<'a> RfcLoadBalancedParameters<'a> {
+    /// Convert to a more generic RfcConnParmHelper structure
+    fn convert(&self) -> Result<RfcConnParmHelper, RfcErrorInfo> {
+        let mut parms = RfcConnParmHelper::new();
+        parms.add("mshost", self.mshost)?;
+        parms.add("msserv", self.msserv)?;
+        parms.add("group", self.group)?;
+        parms.add("r3name", self.r3name)?;
+        parms.add("client", self.client)?;
+        parms.add("user", self.user)?;
+        parms.add("passwd", self.passwd)?;
+        parms.add("lang", self.lang)?;
+        Ok(parms)
+    }
+}
+
+/// An open RFC connection. The connection is stateful: all calls run in
+/// the same ABAP session until it is closed or reset_server_context is
+/// called, so e.g. BAPI_TRANSACTION_COMMIT commits the postings of the
+/// BAPIs called before on the same connection.
 pub struct RfcConnection<'rfclib> {
     connection_handle: *mut RfcConnectionHandle,
-    rfc_lib: &'rfclib RfcLib
+    rfc_lib: &'rfclib RfcLib,
+    /// The parameters the connection was opened with, kept for reconnect
+    parms: RfcConnParmHelper,
+    /// Function descriptors looked up on this connection, by function name
+    function_cache: RefCell<HashMap<String, FunctionDesc>>,
 }
 
+// SAFETY: The NW RFC library allows a connection handle to be used from
+// a different thread than the one that opened it, as long as it is never
+// used by two threads at the same time. Moving an RfcConnection to another
+// thread hands over exclusive use of the handle. RfcConnection is not Sync,
+// and every RfcFunction borrows the connection, so the compiler prevents
+// concurrent use.
+unsafe impl<'rfclib> Send for RfcConnection<'rfclib> {}
+
+/// Cancels the RFC call currently running on a connection, see
+/// RfcConnection::cancel_handle
+pub struct RfcCancelToken<'conn> {
+    connection_handle: *mut RfcConnectionHandle,
+    rfc_lib: &'conn RfcLib,
+    phantom: PhantomData<&'conn RfcConnection<'conn>>,
+}
+
+// SAFETY: RfcCancel is the one RFC library function that may be called
+// on a connection handle while another thread is using it. The token
+// borrows the connection, so the handle cannot be closed while it exists.
+unsafe impl<'conn> Send for RfcCancelToken<'conn> {}
+
+impl<'conn> RfcCancelToken<'conn> {
+    /// Cancel the call running on the connection. The blocked call()
+    /// returns with an error and the connection is closed by the RFC
+    /// library; use reconnect to continue with it.
+    pub fn cancel(&self) -> Result<(), RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcCancel(self.connection_handle, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(())
+    }
+}
+
+/// A connection handle on its way from the thread that opened it, see
+/// connect_with_timeout
+struct SendHandle(*mut RfcConnectionHandle);
+
+// SAFETY: the handle is only used by the receiving thread, see the Send
+// impl of RfcConnection.
+unsafe impl Send for SendHandle {}
+
 /// An RFC function
 pub struct RfcFunction<'conn, 'fun: 'conn> {
-    connection: &'conn RfcConnection<'conn>,
+    rfc_lib: &'conn RfcLib,
+    /// None for a function created from an RfcRepository, which can be
+    /// filled and read but not called
+    connection: Option<&'conn RfcConnection<'conn>>,
+    desc: FunctionDesc,
+    name: String,
     fun: *mut RfcDataContainerHandle,
     fun_desc: Vec<RfcParameter<'conn, 'fun>>,
+    call_state: Rc<CallState>,
 }
 
-    /// Open a conneciton to an SAP system via RFC.
-    /// If you need to specify more RFC connection parameters than
-    /// RfcConnectinoParameters would allow you, use from_parm_helper or from_hashmap
-    /// methods instead.
+/// The loaded NW RFC library.
+///
+/// RfcLib is Send and Sync: it only holds the library handle and the
+/// resolved function pointers, and the NW RFC library may be used from
+/// several threads at once. Wrap it in an Arc to open connections from
+/// different threads.
 pub struct RfcLib {
     rfc_api: dlopen::wrapper::Container<crate::rfc::RfcApi>
 }
 
+// Fail to compile if a future change makes RfcLib lose Send or Sync.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RfcLib>();
+};
+
 
 impl RfcLib {
-    #[cfg(all(target_family = "unix", not(target_vendor = "apple")))]
-    pub fn new() -> Result<RfcLib, String> {
+    /// Load the NW RFC library from the given path, e.g.
+    /// /opt/sap/nwrfcsdk/lib/libsapnwrfc.so
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<RfcLib, String> {
         let rfc_api : dlopen::wrapper::Container<crate::rfc::RfcApi> = unsafe {
-            dlopen::wrapper::Container::load("libsapnwrfc.so")
+            dlopen::wrapper::Container::load(path.as_ref().as_os_str())
         }.map_err(|e| {
             format!("Error trying to load libsapnwrfc: {:?}", e)
         })?;
@@ -78,117 +219,375 @@ impl RfcLib {
         })
     }
 
+    /// Load the NW RFC library using the default search path of the
+    /// dynamic loader
+    #[cfg(all(target_family = "unix", not(target_vendor = "apple")))]
+    pub fn new() -> Result<RfcLib, String> {
+        RfcLib::from_path("libsapnwrfc.so")
+    }
+
+    /// Load the NW RFC library using the default search path of the
+    /// dynamic loader
     #[cfg(all(target_family = "unix", target_vendor = "apple"))]
     pub fn new() -> Result<RfcLib, String> {
-        let rfc_api : dlopen::wrapper::Container<crate::rfc::RfcApi> = unsafe {
-            dlopen::wrapper::Container::load("libsapnwrfc.dylib")
-        }.map_err(|e| {
-            format!("Error trying to load libsapnwrfc: {:?}", e)
-        })?;
-        Ok(RfcLib {
-            rfc_api
-        })
+        RfcLib::from_path("libsapnwrfc.dylib")
     }
 
+    /// Load the NW RFC library using the default search path of the
+    /// dynamic loader
     #[cfg(target_family = "windows")]
     pub fn new() -> Result<RfcLib, String> {
-        let rfc_api : dlopen::wrapper::Container<crate::rfc::RfcApi> = unsafe {
-            dlopen::wrapper::Container::load("sapnwrfc.dll")
-        }.map_err(|e| {
-            format!("Error trying to load libsapnwrfc: {:?}", e)
-        })?;
-        Ok(RfcLib {
-            rfc_api
-        })
+        RfcLib::from_path("sapnwrfc.dll")
+    }
+}
+
+impl RfcLib {
+    /// Return the version of the loaded NW RFC library as major version,
+    /// minor version, patch level and the library's version string.
+    pub fn version(&self) -> (u32, u32, u32, String) {
+        let mut major = 0;
+        let mut minor = 0;
+        let mut patch = 0;
+        let version = unsafe {
+            let v = self.rfc_api.RfcGetVersion(&mut major, &mut minor, &mut patch);
+            if v.is_null() {
+                String::new()
+            } else {
+                U16CString::from_ptr_str(v).to_string_lossy()
+            }
+        };
+        (major, minor, patch, version)
+    }
+
+    /// Open a connection, look up the function `name` and pass it to `f`,
+    /// which sets the inputs, calls the function and reads the outputs.
+    /// The connection is closed afterwards and the result of `f` returned.
+    ///
+    /// ```no_run
+    /// # use rsrfc::*;
+    /// # use rsrfc::connparams::ConnectionBuilder;
+    /// # fn example(rfc_lib: &RfcLib) -> Result<(), rsrfc::error::RfcErrorInfo> {
+    /// let parms = ConnectionBuilder::new().ashost("sap.example.com").sysnr("00").build()?;
+    /// let echo = rfc_lib.call_function(parms, "STFC_CONNECTION", |f| {
+    ///     f.get_mut_parameter("REQUTEXT").unwrap().set_string("Hello")?;
+    ///     f.call()?;
+    ///     f.get_parameter("ECHOTEXT").unwrap().get_chars_trimmed()
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_function<T, F>(&self, parms: RfcConnParmHelper, name: &str, f: F) -> Result<T, RfcErrorInfo>
+    where
+        F: FnOnce(&mut RfcFunction) -> Result<T, RfcErrorInfo>,
+    {
+        let conn = RfcConnection::from_parm_helper(parms, self)?;
+        let res = {
+            let mut fun = conn.get_function(name)?;
+            f(&mut fun)
+        };
+        let closed = conn.close();
+        let value = res?;
+        closed?;
+        Ok(value)
     }
 }
 
 impl <'rfclib>RfcConnection<'rfclib> {
+    /// Open a conneciton to an SAP system via RFC.
+    /// If you need to specify more RFC connection parameters than
+    /// RfcConnectionParameters would allow you, use from_parm_helper or from_hashmap
+    /// methods instead.
     pub fn new<'conn>(conn_info: &RfcConnectionParameters, rfc_lib: &'conn RfcLib) -> Result<RfcConnection<'conn>, RfcErrorInfo> {
-        let parms = conn_info.convert();
+        let parms = conn_info.convert()?;
+        RfcConnection::from_parm_helper(parms, rfc_lib)
+    }
+
+    /// Open a load balanced connection to an SAP system via a message server
+    pub fn new_load_balanced<'conn>(conn_info: &RfcLoadBalancedParameters, rfc_lib: &'conn RfcLib) -> Result<RfcConnection<'conn>, RfcErrorInfo> {
+        let parms = conn_info.convert()?;
         RfcConnection::from_parm_helper(parms, rfc_lib)
     }
 
     /// Open a connection to an SAP system via RFC
     pub fn from_parm_helper<'conn>(parms: RfcConnParmHelper, rfc_lib: &'conn RfcLib) -> Result<RfcConnection<'conn>, RfcErrorInfo> {
+        parms.validate()?;
+        let ch = RfcConnection::open_handle(&parms, rfc_lib)?;
+        Ok(RfcConnection {
+            connection_handle: ch,
+            rfc_lib,
+            parms,
+            function_cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Open a connection like from_parm_helper, but give up after `timeout`.
+    /// The RFC library has no timeout for establishing a connection, so it
+    /// is opened on a separate thread. That thread cannot be interrupted: on
+    /// timeout it is abandoned and may still complete in the background, in
+    /// which case the late connection is closed again right away.
+    pub fn connect_with_timeout<'conn>(parms: RfcConnParmHelper, rfc_lib: &'conn Arc<RfcLib>, timeout: Duration) -> Result<RfcConnection<'conn>, RfcErrorInfo> {
+        parms.validate()?;
+        let (tx, rx) = mpsc::channel();
+        let thread_lib = Arc::clone(rfc_lib);
+        let thread_parms = parms.clone();
+        thread::spawn(move || {
+            let res = RfcConnection::open_handle(&thread_parms, &thread_lib).map(SendHandle);
+            if let Err(mpsc::SendError(Ok(SendHandle(ch)))) = tx.send(res) {
+                let mut err_trunk = RfcErrorInfo::new();
+                unsafe { thread_lib.rfc_api.RfcCloseConnection(ch, &mut err_trunk) };
+            }
+        });
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(SendHandle(ch))) => Ok(RfcConnection {
+                connection_handle: ch,
+                rfc_lib: rfc_lib.as_ref(),
+                parms,
+                function_cache: RefCell::new(HashMap::new()),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let mut err = RfcErrorInfo::custom(&format!("Timeout after {:?} while opening the RFC connection", timeout));
+                err.code = RfcRc::RfcTimeout;
+                Err(err)
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(RfcErrorInfo::custom("The thread opening the RFC connection terminated"))
+            }
+        }
+    }
+
+    fn open_handle(parms: &RfcConnParmHelper, rfc_lib: &RfcLib) -> Result<*mut RfcConnectionHandle, RfcErrorInfo> {
         let mut err_trunk = RfcErrorInfo::new();
-        unsafe {
-            let ch =
-                parms.as_vec(|pv| rfc_lib.rfc_api.RfcOpenConnection(pv.as_ptr(), pv.len() as u32, &mut err_trunk));
-            if ch.is_null() {
-                Err(err_trunk)
-            } else {
-                Ok(RfcConnection {
-                    connection_handle: ch,
-                    rfc_lib
-                })
+        let ch = unsafe {
+            parms.as_vec(|pv| rfc_lib.rfc_api.RfcOpenConnection(pv.as_ptr(), pv.len() as u32, &mut err_trunk))
+        };
+        if ch.is_null() {
+            Err(with_codepage_context(parms, err_trunk))
+        } else {
+            Ok(ch)
+        }
+    }
+
+    /// Check that the connection is still alive with a round trip to the
+    /// SAP system
+    pub fn ping(&self) -> Result<(), RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcPing(self.connection_handle, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(())
+    }
+
+    /// Return true if the connection handle was not closed yet. This does
+    /// not check whether the SAP system is still reachable; use ping for that.
+    pub fn is_open(&self) -> bool {
+        !self.connection_handle.is_null()
+    }
+
+    /// Close the connection and return any error of the RFC library,
+    /// instead of only printing it as dropping the connection does.
+    pub fn close(mut self) -> Result<(), RfcErrorInfo> {
+        let res = self.close_handle();
+        // The handle is unusable either way, don't try again on drop
+        self.connection_handle = std::ptr::null_mut();
+        res
+    }
+
+    /// Close the connection handle, if it is still open. The handle is
+    /// only reset after a successful close.
+    fn close_handle(&mut self) -> Result<(), RfcErrorInfo> {
+        if self.connection_handle.is_null() {
+            return Ok(());
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcCloseConnection(self.connection_handle, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.connection_handle = std::ptr::null_mut();
+        Ok(())
+    }
+
+    /// End the ABAP session of the connection without closing it. Global
+    /// data of the called function groups is discarded, and uncommitted
+    /// postings are rolled back, so the next call starts in a fresh session.
+    pub fn reset_server_context(&self) -> Result<(), RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcResetServerContext(self.connection_handle, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(())
+    }
+
+    /// Return a token to cancel a running call from another thread. The
+    /// token can be sent to a different thread than the one blocked in
+    /// call(), e.g. a watchdog started with std::thread::scope.
+    pub fn cancel_handle(&self) -> RfcCancelToken<'_> {
+        RfcCancelToken {
+            connection_handle: self.connection_handle,
+            rfc_lib: self.rfc_lib,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Close the connection and open it again with the parameters it was
+    /// created with, e.g. after an RfcCommunicationFailure left the handle
+    /// unusable. Errors while closing the old handle are ignored.
+    pub fn reconnect(&mut self) -> Result<(), RfcErrorInfo> {
+        if !self.connection_handle.is_null() {
+            let mut err_trunk = RfcErrorInfo::new();
+            unsafe { self.rfc_lib.rfc_api.RfcCloseConnection(self.connection_handle, &mut err_trunk) };
+            self.connection_handle = std::ptr::null_mut();
+        }
+        self.connection_handle = RfcConnection::open_handle(&self.parms, self.rfc_lib)?;
+        Ok(())
+    }
+
+    /// Ping the connection and reconnect if the ping fails, e.g. before
+    /// using a connection that was idle for longer than the idle timeout
+    /// of the gateway. After a reconnect the connection runs in a new ABAP
+    /// session. Returns the error of the reconnect if that fails too.
+    pub fn ping_or_reconnect(&mut self) -> Result<(), RfcErrorInfo> {
+        if self.ping().is_ok() {
+            return Ok(());
+        }
+        self.reconnect()
+    }
+
+    /// Look up the function `name` and pass it to `f`, which sets the
+    /// inputs, calls the function and reads the outputs, like
+    /// RfcLib::call_function. If `f` fails with an error classified as
+    /// RfcError::Communication or RfcError::Timeout, wait for `backoff`,
+    /// reconnect and try again with a new function instance, up to
+    /// `attempts` times in total. Each retry runs in a new ABAP session.
+    /// Other errors, e.g. logon failures or ABAP exceptions, are returned
+    /// right away.
+    ///
+    /// This lives on the connection rather than on RfcFunction: reconnecting
+    /// replaces the connection handle and needs `&mut self`, while every
+    /// RfcFunction holds a shared borrow of its connection. The function is
+    /// looked up again after each reconnect, so `f` sets its inputs anew.
+    pub fn call_with_retry<T, F>(
+        &mut self,
+        name: &str,
+        attempts: usize,
+        backoff: Duration,
+        mut f: F,
+    ) -> Result<T, RfcErrorInfo>
+    where
+        F: FnMut(&mut RfcFunction) -> Result<T, RfcErrorInfo>,
+    {
+        let mut attempt = 1;
+        let mut res = self.call_once(name, &mut f);
+        while let Err(e) = &res {
+            let transient = matches!(e.classify(), RfcError::Communication | RfcError::Timeout);
+            if !transient || attempt >= attempts {
+                break;
             }
+            attempt += 1;
+            thread::sleep(backoff);
+            res = match self.reconnect() {
+                Ok(()) => self.call_once(name, &mut f),
+                Err(e) => Err(e),
+            };
         }
+        res
+    }
+
+    fn call_once<T, F>(&self, name: &str, f: &mut F) -> Result<T, RfcErrorInfo>
+    where
+        F: FnMut(&mut RfcFunction) -> Result<T, RfcErrorInfo>,
+    {
+        let mut fun = self.get_function(name)?;
+        f(&mut fun)
     }
 
     /// Open a connection to an SAP system via RFC
     pub fn from_hashmap<'conn>(parms: &HashMap<String, String>, rfc_lib: &'conn RfcLib) -> Result<RfcConnection<'conn>, RfcErrorInfo> {
         let mut ph = RfcConnParmHelper::new();
         for (k, v) in parms {
-            ph.add(k, v);
+            ph.add(k, v)?;
         }
         RfcConnection::from_parm_helper(ph, rfc_lib)
     }
 
-    /// Return a reference to an RFC enabled function, if it exists on
-    /// the remote system.
-    pub fn get_function<'conn, 'fun: 'conn>(
-        &'conn self,
-        name: &str,
-    ) -> Result<RfcFunction<'conn, 'fun>, RfcErrorInfo> {
-        let name_uc = U16CString::from_str(name).unwrap().into_vec_with_nul();
+    /// Fetch the signature of a function module without creating a
+    /// function instance. The result does not borrow the connection.
+    pub fn describe_function(&self, name: &str) -> Result<FunctionMetadata, RfcErrorInfo> {
+        let desc = self.function_desc(name)?;
+        Ok(FunctionMetadata {
+            name: name.to_uppercase(),
+            parameters: desc.parameters.iter().map(ParameterMetadata::from_desc).collect(),
+        })
+    }
+
+    /// Look up the descriptor of a function and its parameters. The result
+    /// is cached, so repeated lookups of the same function skip the
+    /// round trips to the SAP system and the decoding of the parameters.
+    fn function_desc(&self, name: &str) -> Result<FunctionDesc, RfcErrorInfo> {
+        let key = name.to_uppercase();
+        if let Some(desc) = self.function_cache.borrow().get(&key) {
+            return Ok(desc.clone());
+        }
+        let name_uc = U16CString::from_str(name).map_err(|e| interior_nul("Function name", e))?.into_vec_with_nul();
         let mut err_trunk = RfcErrorInfo::new();
         unsafe {
             let fd = self.rfc_lib.rfc_api.RfcGetFunctionDesc(self.connection_handle, name_uc.as_ptr(), &mut err_trunk);
             if fd.is_null() {
                 return Err(err_trunk);
             }
-            let ff = self.rfc_lib.rfc_api.RfcCreateFunction(fd, &mut err_trunk);
-            if ff.is_null() {
-                return Err(err_trunk);
-            }
-            /*
-            let fftd = unsafe { RfcDescribeType(ff, &mut err_trunk) };
-            if fftd.is_null() {
-                return Err(err_trunk);
-            }
-            let fun_desc = RfcDecodedFieldDesc::from_handle(fftd)?;
-            */
-            let mut parm_count: u32 = 0;
-            let res = self.rfc_lib.rfc_api.RfcGetParameterCount(fd, &mut parm_count, &mut err_trunk);
-            if !res.is_ok() {
-                return Err(err_trunk);
-            }
-
-            let mut fun_desc = Vec::new();
-            {
-                let mut rpd = RfcParameterDesc::new(&self.rfc_lib.rfc_api);
-                fun_desc.reserve_exact(parm_count as usize);
-                for i in 0..parm_count {
-                    let res = self.rfc_lib.rfc_api.RfcGetParameterDescByIndex(fd, i, &mut rpd, &mut err_trunk);
-                    if !res.is_ok() {
-                        return Err(err_trunk);
-                    }
-                    let parm = rpd.to_parameter(i, ff)?;
-                    fun_desc.push(parm);
-                }
-            }
-
-            Ok(RfcFunction {
-                connection: self,
-                fun: ff,
-                fun_desc,
-            })
+            let parameters = parameter_descs(self.rfc_lib, fd)?;
+            let desc = FunctionDesc::from_cache(fd, parameters);
+            self.function_cache.borrow_mut().insert(key, desc.clone());
+            Ok(desc)
         }
     }
+
+    /// Return a reference to an RFC enabled function, if it exists on
+    /// the remote system.
+    pub fn get_function<'conn, 'fun: 'conn>(
+        &'conn self,
+        name: &str,
+    ) -> Result<RfcFunction<'conn, 'fun>, RfcErrorInfo> {
+        let desc = self.function_desc(name)?;
+        RfcFunction::new(self.rfc_lib, Some(self), name, desc)
+    }
 }
 
 impl<'conn, 'fun> RfcFunction<'conn, 'fun> {
+    /// Create the data container for a function and decode its parameters
+    pub(crate) fn new(
+        rfc_lib: &'conn RfcLib,
+        connection: Option<&'conn RfcConnection<'conn>>,
+        name: &str,
+        desc: FunctionDesc,
+    ) -> Result<RfcFunction<'conn, 'fun>, RfcErrorInfo> {
+        let (fun, mut fun_desc) = create_function(rfc_lib, &desc)?;
+        let call_state = Rc::new(CallState::default());
+        attach_call_state(&mut fun_desc, &call_state);
+        Ok(RfcFunction {
+            rfc_lib,
+            connection,
+            desc,
+            name: name.to_uppercase(),
+            fun,
+            fun_desc,
+            call_state,
+        })
+    }
+
+    /// The connection to call the function on
+    pub(crate) fn connection(&self) -> Result<&'conn RfcConnection<'conn>, RfcErrorInfo> {
+        self.connection.ok_or_else(|| {
+            RfcErrorInfo::custom(&format!(
+                "Function {} was created from a repository and cannot be called",
+                self.name
+            ))
+        })
+    }
+
     /// Get a reference to an RFC parameter using the parameter index.
     pub fn get_parameter_by_index(
         &mut self,
@@ -222,28 +621,242 @@ impl<'conn, 'fun> RfcFunction<'conn, 'fun> {
         None
     }
 
+    /// All parameters of the function, in declaration order
+    pub fn parameters(&self) -> &[RfcParameter<'conn, 'fun>] {
+        &self.fun_desc
+    }
+
+    /// Names of all parameters of the function, in declaration order
+    pub fn parameter_names(&self) -> Vec<&str> {
+        self.fun_desc.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    /// Parameters the caller can write: IMPORTING, CHANGING and TABLES
+    pub fn import_parameters(&self) -> impl Iterator<Item = &RfcParameter<'conn, 'fun>> {
+        self.fun_desc.iter().filter(|p| p.direction.can_write())
+    }
+
+    /// Parameters the caller can read: EXPORTING, CHANGING and TABLES
+    pub fn export_parameters(&self) -> impl Iterator<Item = &RfcParameter<'conn, 'fun>> {
+        self.fun_desc.iter().filter(|p| p.direction.can_read())
+    }
+
+    /// Read the values of all EXPORTING and CHANGING parameters after a
+    /// call, by parameter name. Types that get_value does not support, like
+    /// UTCLONG, are read with get_string and returned as RfcString.
+    /// Structures, tables, boxed and object parameters are skipped; read
+    /// structures and tables with get_field or rows.
+    pub fn exports(&self) -> Result<HashMap<String, RfcValue>, RfcErrorInfo> {
+        let mut values = HashMap::new();
+        for p in self.export_parameters() {
+            let value = match p.field_type {
+                RfcType::XmlData
+                | RfcType::UtcLong
+                | RfcType::UtcSecond
+                | RfcType::UtcMinute
+                | RfcType::DtDay
+                | RfcType::DtMonth
+                | RfcType::TSecond
+                | RfcType::TMinute
+                | RfcType::CDay => RfcValue::RfcString(p.get_string()?),
+                RfcType::Structure
+                | RfcType::Table
+                | RfcType::Null
+                | RfcType::AbapObject
+                | RfcType::Box
+                | RfcType::GenericBox
+                | RfcType::Unknown(_) => continue,
+                _ => p.get_value()?,
+            };
+            values.insert(p.name.clone(), value);
+        }
+        Ok(values)
+    }
+
+    /// Like get_parameter, but returns an error naming the parameter if it
+    /// does not exist.
+    fn parameter_or_err(&self, parameter_name: &str) -> Result<&RfcParameter<'conn, 'fun>, RfcErrorInfo> {
+        self.get_parameter(parameter_name).ok_or_else(|| {
+            RfcErrorInfo::custom(&format!("unknown parameter {}", parameter_name))
+        })
+    }
+
+    /// Like get_mut_parameter, but returns an error naming the parameter if
+    /// it does not exist.
+    fn parameter_mut_or_err(
+        &mut self,
+        parameter_name: &str,
+    ) -> Result<&mut RfcParameter<'conn, 'fun>, RfcErrorInfo> {
+        self.get_mut_parameter(parameter_name).ok_or_else(|| {
+            RfcErrorInfo::custom(&format!("unknown parameter {}", parameter_name))
+        })
+    }
+
+    /// Set a STRING or CHAR parameter by name. This is a case insensitive
+    /// operation.
+    pub fn set_string_by_name(&mut self, parameter_name: &str, value: &str) -> Result<(), RfcErrorInfo> {
+        self.parameter_mut_or_err(parameter_name)?.set_string(value)
+    }
+
+    /// Set an integer parameter by name. This is a case insensitive
+    /// operation.
+    pub fn set_int_by_name(&mut self, parameter_name: &str, value: i64) -> Result<(), RfcErrorInfo> {
+        self.parameter_mut_or_err(parameter_name)?.set_int(value)
+    }
+
+    /// Get a CHAR parameter by name. This is a case insensitive operation.
+    pub fn get_chars_by_name(&self, parameter_name: &str) -> Result<String, RfcErrorInfo> {
+        self.parameter_or_err(parameter_name)?.get_chars()
+    }
+
+    /// Get a STRING parameter by name. This is a case insensitive operation.
+    pub fn get_string_by_name(&self, parameter_name: &str) -> Result<String, RfcErrorInfo> {
+        self.parameter_or_err(parameter_name)?.get_string()
+    }
+
+    /// Get an integer parameter by name. This is a case insensitive
+    /// operation.
+    pub fn get_int_by_name(&self, parameter_name: &str) -> Result<i64, RfcErrorInfo> {
+        self.parameter_or_err(parameter_name)?.get_int()
+    }
+
+    /// In strict mode, reading an EXPORTING parameter or one of its fields
+    /// before the function was called successfully is an error instead of
+    /// returning the initial value. Off by default.
+    pub fn strict(&mut self, strict: bool) {
+        self.call_state.strict.set(strict);
+    }
+
+    /// Clear all parameters by replacing the data container of the function
+    /// with a fresh one, e.g. to call the function again with new inputs.
+    /// The parameter layout comes from the descriptor the function was
+    /// created with, so no round trip to the SAP system is needed.
+    pub fn reset(&mut self) -> Result<(), RfcErrorInfo> {
+        let (fun, mut fun_desc) = create_function(self.rfc_lib, &self.desc)?;
+        attach_call_state(&mut fun_desc, &self.call_state);
+        self.call_state.called.set(false);
+        let old = std::mem::replace(&mut self.fun, fun);
+        self.fun_desc = fun_desc;
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcDestroyFunction(old, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(())
+    }
+
+    /// The call changes the tables, so cached row counts are outdated
+    pub(crate) fn forget_row_counts(&self) {
+        for p in &self.fun_desc {
+            p.forget_row_count();
+        }
+    }
+
     /// Call the remote function
     pub fn call(&mut self) -> Result<(), RfcErrorInfo> {
+        let connection = self.connection()?;
         let mut err_trunk = RfcErrorInfo::new();
-        let res = unsafe { self.connection.rfc_lib.rfc_api.RfcInvoke(self.connection.connection_handle, self.fun, &mut err_trunk) };
+        let res = unsafe { self.rfc_lib.rfc_api.RfcInvoke(connection.connection_handle, self.fun, &mut err_trunk) };
+        self.forget_row_counts();
         if !res.is_ok() {
-            return Err(err_trunk);
+            return Err(with_codepage_context(&connection.parms, err_trunk));
         }
+        self.call_state.called.set(true);
         Ok(())
     }
+
+    /// Call the remote function after checking that every mandatory
+    /// IMPORTING parameter was set. Missing parameters are reported by name
+    /// instead of surfacing as an ABAP runtime error.
+    pub fn call_checked(&mut self) -> Result<(), RfcErrorInfo> {
+        let missing: Vec<&str> = self
+            .fun_desc
+            .iter()
+            .filter(|p| p.direction == RfcDirection::RfcImport && !p.is_optional() && !p.was_written())
+            .map(|p| p.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Mandatory parameters not set: {}",
+                missing.join(", ")
+            )));
+        }
+        self.call()
+    }
+}
+
+/// Create a data container for a function and decode its parameters
+fn create_function<'conn, 'fun: 'conn>(
+    rfc_lib: &'conn RfcLib,
+    desc: &FunctionDesc,
+) -> Result<(*mut RfcDataContainerHandle, Vec<RfcParameter<'conn, 'fun>>), RfcErrorInfo> {
+    let mut err_trunk = RfcErrorInfo::new();
+    let ff = unsafe { rfc_lib.rfc_api.RfcCreateFunction(desc.as_ptr(), &mut err_trunk) };
+    if ff.is_null() {
+        return Err(err_trunk);
+    }
+
+    let mut fun_desc = Vec::with_capacity(desc.parameters.len());
+    for (i, rpd) in desc.parameters.iter().enumerate() {
+        match rpd.to_parameter(&rfc_lib.rfc_api, i as u32, ff) {
+            Ok(p) => fun_desc.push(p),
+            Err(e) => {
+                unsafe { rfc_lib.rfc_api.RfcDestroyFunction(ff, &mut err_trunk) };
+                return Err(e);
+            }
+        }
+    }
+    Ok((ff, fun_desc))
+}
+
+/// Read the descriptions of all parameters of a function descriptor
+pub(crate) fn parameter_descs(
+    rfc_lib: &RfcLib,
+    fd: *mut RfcFunctionDescHandle,
+) -> Result<Vec<RfcParameterDesc>, RfcErrorInfo> {
+    let mut err_trunk = RfcErrorInfo::new();
+    let mut parm_count: u32 = 0;
+    let res = unsafe { rfc_lib.rfc_api.RfcGetParameterCount(fd, &mut parm_count, &mut err_trunk) };
+    if !res.is_ok() {
+        return Err(err_trunk);
+    }
+
+    let mut parameters = Vec::with_capacity(parm_count as usize);
+    let mut rpd = RfcParameterDesc::new();
+    for i in 0..parm_count {
+        let res = unsafe { rfc_lib.rfc_api.RfcGetParameterDescByIndex(fd, i, &mut rpd, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        parameters.push(rpd.clone());
+    }
+    Ok(parameters)
+}
+
+/// Name the codepage of the connection in a codepage conversion error,
+/// which the RFC library reports without any context
+pub(crate) fn with_codepage_context(parms: &RfcConnParmHelper, mut err: RfcErrorInfo) -> RfcErrorInfo {
+    if err.code == RfcRc::RfcCodepageConversionFailure {
+        let codepage = parms.get("codepage").unwrap_or_else(|| "default".to_string());
+        err.set_message(&format!("{} (codepage {})", err.message(), codepage));
+    }
+    err
+}
+
+/// Share the call state of a function with its EXPORTING parameters
+fn attach_call_state(fun_desc: &mut [RfcParameter], state: &Rc<CallState>) {
+    for p in fun_desc.iter_mut().filter(|p| p.direction == RfcDirection::RfcExport) {
+        p.set_call_state(state);
+    }
 }
 
 impl <'rfclib> Drop for RfcConnection<'rfclib> {
     fn drop(&mut self) {
-        if !self.connection_handle.is_null() {
-            let mut err_trunk = RfcErrorInfo::new();
-            let res = unsafe { self.rfc_lib.rfc_api.RfcCloseConnection(self.connection_handle, &mut err_trunk) };
-            if !res.is_ok() {
-                eprintln!(
-                    "Warning: Unable to close RFC connection: {}",
-                    String::from_utf16_lossy(&err_trunk.message)
-                );
-            }
+        if let Err(err_trunk) = self.close_handle() {
+            eprintln!(
+                "Warning: Unable to close RFC connection: {}",
+                String::from_utf16_lossy(&err_trunk.message)
+            );
         }
     }
 }
@@ -252,7 +865,7 @@ impl<'conn, 'fun> Drop for RfcFunction<'conn, 'fun> {
     fn drop(&mut self) {
         if !self.fun.is_null() {
             let mut err_trunk = RfcErrorInfo::new();
-            let res = unsafe { self.connection.rfc_lib.rfc_api.RfcDestroyFunction(self.fun, &mut err_trunk) };
+            let res = unsafe { self.rfc_lib.rfc_api.RfcDestroyFunction(self.fun, &mut err_trunk) };
             if !res.is_ok() {
                 eprintln!(
                     "Warning: Unable to destroy RFC function: {}",
@@ -262,3 +875,41 @@ impl<'conn, 'fun> Drop for RfcFunction<'conn, 'fun> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Connection parameters of a test system, from the environment
+    /// variables RSRFC_ASHOST, RSRFC_SYSNR, RSRFC_CLIENT, RSRFC_USER and
+    /// RSRFC_PASSWD
+    fn test_parms() -> RfcConnParmHelper {
+        let var = |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{} is not set", name));
+        ConnectionBuilder::new()
+            .ashost(&var("RSRFC_ASHOST"))
+            .sysnr(&var("RSRFC_SYSNR"))
+            .client(&var("RSRFC_CLIENT"))
+            .user(&var("RSRFC_USER"))
+            .passwd(&var("RSRFC_PASSWD"))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc and an SAP system"]
+    fn connect_with_timeout_from_two_threads() {
+        let lib = Arc::new(RfcLib::new().expect("libsapnwrfc not found"));
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let lib = Arc::clone(&lib);
+                thread::spawn(move || {
+                    let conn = RfcConnection::connect_with_timeout(test_parms(), &lib, Duration::from_secs(30)).unwrap();
+                    conn.ping().unwrap();
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+}
@@ -32,12 +32,7 @@ fn main() -> Result<(), RfcErrorInfo>{
     {
         // Get the RFC_READ_TABLE function
         let mut rfc_read_table = conn.get_function("RFC_READ_TABLE").expect("RFC_READ_TABLE");
-        {
-            let query_table = rfc_read_table
-                .get_mut_parameter("QUERY_TABLE")
-                .ok_or(RfcErrorInfo::custom("unknown field QUERY_TABLE"))?;
-            query_table.set_string("USR02")?;
-        }
+        rfc_read_table.set_string_by_name("QUERY_TABLE", "USR02")?;
 
         // The field we are interested in is called BNAME.
         // Tell this to the RFC_READ_TABLE function.
@@ -72,8 +67,8 @@ fn main() -> Result<(), RfcErrorInfo>{
             data.set_row(i)?;
             let row_content = data
                 .get_field_by_index(idx_wa)?
-                .get_chars()?;
-            println!("Username: {}", row_content.trim_end());
+                .get_chars_trimmed()?;
+            println!("Username: {}", row_content);
         }
     }
 
@@ -0,0 +1,54 @@
+//! Function signatures that can be inspected without creating a function
+//! instance, e.g. for code generation.
+
+use crate::error::*;
+use crate::rfc::*;
+
+/// The signature of an RFC enabled function
+#[derive(Debug, Clone)]
+pub struct FunctionMetadata {
+    pub name: String,
+    pub parameters: Vec<ParameterMetadata>,
+}
+
+/// Description of one parameter of an RFC enabled function
+#[derive(Debug, Clone)]
+pub struct ParameterMetadata {
+    pub name: String,
+    pub field_type: RfcType,
+    pub direction: RfcDirection,
+    /// Length in bytes in the non-unicode layout
+    pub nuc_length: u32,
+    /// Length in bytes in the unicode layout
+    pub uc_length: u32,
+    pub decimals: u32,
+    pub optional: bool,
+    pub default_value: Option<String>,
+    /// Short text of the parameter as maintained in the function module
+    pub description: Option<String>,
+}
+
+impl FunctionMetadata {
+    /// Find a parameter by name, ignoring case
+    pub fn parameter(&self, name: &str) -> Option<&ParameterMetadata> {
+        self.parameters.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl ParameterMetadata {
+    pub(crate) fn from_desc(desc: &RfcParameterDesc) -> ParameterMetadata {
+        let default_value = decode_buf(&desc.default_value);
+        let description = decode_buf(&desc.parameter_text);
+        ParameterMetadata {
+            name: decode_buf(&desc.name),
+            field_type: RfcType::from_raw(desc.field_type),
+            direction: desc.direction,
+            nuc_length: desc.nuc_length,
+            uc_length: desc.uc_length,
+            decimals: desc.decimals,
+            optional: desc.optional != 0,
+            default_value: if default_value.is_empty() { None } else { Some(default_value) },
+            description: if description.is_empty() { None } else { Some(description) },
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! A pool of RFC connections for servers that call RFC functions per
+//! request, e.g. from an actix or axum handler.
+
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use crate::connparams::RfcConnParmHelper;
+use crate::error::RfcErrorInfo;
+use crate::{RfcConnection, RfcLib};
+
+struct PoolState {
+    // The connections borrow the RfcLib owned by the pool. They are only
+    // handed out behind a PooledConnection, which cannot outlive the pool,
+    // and are dropped before the pool's Arc<RfcLib>.
+    idle: Vec<RfcConnection<'static>>,
+    /// Number of open connections, idle or checked out
+    open: usize,
+}
+
+/// A pool of connections to one SAP system. Connections are opened lazily
+/// up to `max_size`; when all of them are in use, acquire waits for one to
+/// be returned.
+pub struct RfcConnectionPool {
+    state: Mutex<PoolState>,
+    returned: Condvar,
+    parms: RfcConnParmHelper,
+    max_size: usize,
+    rfc_lib: Arc<RfcLib>,
+}
+
+impl RfcConnectionPool {
+    /// Create an empty pool that opens at most `max_size` connections with
+    /// the given parameters
+    pub fn new(rfc_lib: Arc<RfcLib>, parms: RfcConnParmHelper, max_size: usize) -> Result<RfcConnectionPool, RfcErrorInfo> {
+        parms.validate()?;
+        if max_size == 0 {
+            return Err(RfcErrorInfo::custom("max_size of a connection pool must be at least 1"));
+        }
+        Ok(RfcConnectionPool {
+            state: Mutex::new(PoolState { idle: Vec::new(), open: 0 }),
+            returned: Condvar::new(),
+            parms,
+            max_size,
+            rfc_lib,
+        })
+    }
+
+    /// Take a connection from the pool, opening a new one if none is idle
+    /// and the pool is not full yet. Idle connections are pinged on checkout
+    /// and reconnected if the ping fails. The connection goes back to the
+    /// pool when the returned guard is dropped.
+    pub fn acquire(&self) -> Result<PooledConnection<'_>, RfcErrorInfo> {
+        let mut state = self.lock();
+        loop {
+            if let Some(mut conn) = state.idle.pop() {
+                drop(state);
+                if let Err(e) = conn.ping_or_reconnect() {
+                    drop(conn);
+                    self.release_slot();
+                    return Err(e);
+                }
+                return Ok(PooledConnection { pool: self, conn: Some(conn) });
+            }
+            if state.open < self.max_size {
+                state.open += 1;
+                drop(state);
+                return match self.open() {
+                    Ok(conn) => Ok(PooledConnection { pool: self, conn: Some(conn) }),
+                    Err(e) => {
+                        self.release_slot();
+                        Err(e)
+                    }
+                };
+            }
+            state = self.returned.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Number of open connections, idle or in use
+    pub fn size(&self) -> usize {
+        self.lock().open
+    }
+
+    /// Number of idle connections
+    pub fn idle(&self) -> usize {
+        self.lock().idle.len()
+    }
+
+    fn open(&self) -> Result<RfcConnection<'static>, RfcErrorInfo> {
+        // SAFETY: see PoolState; the pool keeps the library loaded for as
+        // long as any of its connections exists.
+        let rfc_lib: &'static RfcLib = unsafe { &*Arc::as_ptr(&self.rfc_lib) };
+        RfcConnection::from_parm_helper(self.parms.clone(), rfc_lib)
+    }
+
+    /// Forget a connection that could not be opened or reopened
+    fn release_slot(&self) {
+        self.lock().open -= 1;
+        self.returned.notify_one();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A connection checked out from an RfcConnectionPool. It is returned to
+/// the pool when dropped.
+pub struct PooledConnection<'pool> {
+    pool: &'pool RfcConnectionPool,
+    conn: Option<RfcConnection<'static>>,
+}
+
+impl<'pool> Deref for PooledConnection<'pool> {
+    type Target = RfcConnection<'pool>;
+
+    fn deref(&self) -> &RfcConnection<'pool> {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl<'pool> Drop for PooledConnection<'pool> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.lock().idle.push(conn);
+            self.pool.returned.notify_one();
+        }
+    }
+}
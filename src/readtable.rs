@@ -0,0 +1,158 @@
+//! Convenience wrapper around RFC_READ_TABLE, the generic function module
+//! for reading rows of a database table.
+
+use std::collections::HashMap;
+
+use crate::error::RfcErrorInfo;
+use crate::RfcConnection;
+
+/// Maximum length of a line of the WHERE clause (OPTIONS-TEXT)
+const MAX_WHERE_LINE: usize = 72;
+
+/// Delimiter used by read_table to separate the columns of a row
+pub const DEFAULT_DELIMITER: char = '|';
+
+/// Read rows of a database table with RFC_READ_TABLE. `fields` selects the
+/// columns; all columns are read if it is empty. `where_clause` holds the
+/// lines of an ABAP WHERE condition, at most 72 characters each, e.g.
+/// `&["BNAME LIKE 'A%'"]`. Each row is returned as a map of field name to
+/// the value with trailing blanks removed.
+///
+/// RFC_READ_TABLE returns rows of at most 512 characters, so selecting many
+/// or wide columns fails with an error naming the limit.
+///
+/// The columns are separated by DEFAULT_DELIMITER; use
+/// read_table_with_delimiter if the data may contain that character.
+pub fn read_table(
+    conn: &RfcConnection,
+    table: &str,
+    fields: &[&str],
+    where_clause: &[&str],
+) -> Result<Vec<HashMap<String, String>>, RfcErrorInfo> {
+    read_table_with_delimiter(conn, table, fields, where_clause, DEFAULT_DELIMITER)
+}
+
+/// Like read_table, but with the given character separating the columns
+/// of the rows returned by RFC_READ_TABLE. Rows in which a value contains
+/// the delimiter cannot be split and are reported as an error.
+pub fn read_table_with_delimiter(
+    conn: &RfcConnection,
+    table: &str,
+    fields: &[&str],
+    where_clause: &[&str],
+    delimiter: char,
+) -> Result<Vec<HashMap<String, String>>, RfcErrorInfo> {
+    let mut fun = conn.get_function("RFC_READ_TABLE")?;
+    fun.set_string_by_name("QUERY_TABLE", table)?;
+    fun.set_string_by_name("DELIMITER", &delimiter.to_string())?;
+    {
+        let options = fun
+            .get_mut_parameter("OPTIONS")
+            .ok_or_else(|| RfcErrorInfo::custom("unknown parameter OPTIONS"))?;
+        for line in where_clause {
+            if line.chars().count() > MAX_WHERE_LINE {
+                return Err(RfcErrorInfo::custom(&format!(
+                    "WHERE clause line longer than {} characters: {}",
+                    MAX_WHERE_LINE, line
+                )));
+            }
+            options.append_rows(1)?;
+            options.last_row()?;
+            options.get_field_mut("TEXT")?.set_string(line)?;
+        }
+    }
+    {
+        let fields_parm = fun
+            .get_mut_parameter("FIELDS")
+            .ok_or_else(|| RfcErrorInfo::custom("unknown parameter FIELDS"))?;
+        for field in fields {
+            fields_parm.append_rows(1)?;
+            fields_parm.last_row()?;
+            fields_parm.get_field_mut("FIELDNAME")?.set_string(field)?;
+        }
+    }
+
+    if let Err(e) = fun.call() {
+        if e.key() == "DATA_BUFFER_EXCEEDED" {
+            return Err(RfcErrorInfo::custom(&format!(
+                "The selected fields of {} exceed the row width of 512 characters \
+                 of RFC_READ_TABLE; select fewer fields",
+                table
+            )));
+        }
+        return Err(e);
+    }
+
+    // FIELDS holds the names of the columns in the order of the rows
+    let fields_parm = fun
+        .get_parameter("FIELDS")
+        .ok_or_else(|| RfcErrorInfo::custom("unknown parameter FIELDS"))?;
+    let mut columns = Vec::new();
+    for i in 0..fields_parm.get_row_count()? {
+        fields_parm.set_row(i)?;
+        columns.push(fields_parm.get_field("FIELDNAME")?.get_chars_trimmed()?);
+    }
+
+    let data = fun
+        .get_parameter("DATA")
+        .ok_or_else(|| RfcErrorInfo::custom("unknown parameter DATA"))?;
+    let row_count = data.get_row_count()?;
+    let mut rows = Vec::with_capacity(row_count as usize);
+    for i in 0..row_count {
+        data.set_row(i)?;
+        let wa = data.get_field("WA")?.get_chars()?;
+        let row = split_row(&wa, &columns, delimiter).map_err(|values| {
+            RfcErrorInfo::custom(&format!(
+                "Row {} of {} has {} values for {} columns; a value probably \
+                 contains the delimiter {:?}",
+                i,
+                table,
+                values,
+                columns.len(),
+                delimiter
+            ))
+        })?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Split a row of RFC_READ_TABLE into a map of column name to value with
+/// trailing blanks removed. Returns the number of values if it does not
+/// match the number of columns.
+fn split_row(wa: &str, columns: &[String], delimiter: char) -> Result<HashMap<String, String>, usize> {
+    let values: Vec<&str> = wa.split(delimiter).collect();
+    if values.len() != columns.len() {
+        return Err(values.len());
+    }
+    Ok(columns
+        .iter()
+        .zip(values)
+        .map(|(name, value)| (name.clone(), value.trim_end().to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn split_row_trims_values() {
+        let row = split_row("ALICE     |001|   ", &columns(&["BNAME", "MANDT", "CLASS"]), '|').unwrap();
+        assert_eq!(row["BNAME"], "ALICE");
+        assert_eq!(row["MANDT"], "001");
+        assert_eq!(row["CLASS"], "");
+    }
+
+    #[test]
+    fn split_row_reports_delimiter_in_value() {
+        let res = split_row("A|B|C", &columns(&["X", "Y"]), '|');
+        assert_eq!(res.unwrap_err(), 3);
+        assert!(split_row("A|B|C", &columns(&["X", "Y"]), ';').is_err());
+        assert!(split_row("A;B|C", &columns(&["X", "Y"]), ';').is_ok());
+    }
+}
@@ -0,0 +1,369 @@
+//! Function descriptions built in code instead of being fetched from an
+//! SAP system, e.g. to describe functions while the system is not
+//! reachable, or for code generation.
+
+use std::collections::HashMap;
+
+use widestring::U16CString;
+
+use crate::error::*;
+use crate::metadata::*;
+use crate::rfc::*;
+use crate::{parameter_descs, RfcConnection, RfcFunction, RfcLib};
+
+/// Field of a structure type, see RfcRepository::add_type
+pub struct FieldSpec<'a> {
+    pub name: &'a str,
+    pub field_type: RfcType,
+    /// Length in bytes in the non-unicode layout
+    pub nuc_length: u32,
+    /// Length in bytes in the unicode layout
+    pub uc_length: u32,
+    pub decimals: u32,
+    /// Name of a type added before, for structure and table fields
+    pub type_name: Option<&'a str>,
+}
+
+/// Parameter of a function, see RfcRepository::add_function
+pub struct ParameterSpec<'a> {
+    pub name: &'a str,
+    pub field_type: RfcType,
+    pub direction: RfcDirection,
+    /// Length in bytes in the non-unicode layout
+    pub nuc_length: u32,
+    /// Length in bytes in the unicode layout
+    pub uc_length: u32,
+    pub decimals: u32,
+    pub optional: bool,
+    /// Name of a type added before, for structure and table parameters
+    pub type_name: Option<&'a str>,
+}
+
+/// A structure type with the alignment of its layouts
+struct TypeEntry {
+    handle: *mut RfcDataContainerHandle,
+    nuc_align: u32,
+    uc_align: u32,
+}
+
+/// A repository of types and function descriptions. The descriptions are
+/// handed over to the metadata cache of the RFC library under the given
+/// repository ID, and can be used by a connection with use_repository
+/// without looking them up in the SAP system.
+pub struct RfcRepository<'lib> {
+    rfc_lib: &'lib RfcLib,
+    id: U16CString,
+    types: HashMap<String, TypeEntry>,
+    functions: HashMap<String, FunctionDesc>,
+}
+
+impl<'lib> RfcRepository<'lib> {
+    /// Create an empty repository. `repository_id` is usually the system ID
+    /// of the SAP system the descriptions belong to.
+    pub fn new(rfc_lib: &'lib RfcLib, repository_id: &str) -> Result<RfcRepository<'lib>, RfcErrorInfo> {
+        let id = U16CString::from_str(repository_id).map_err(|e| interior_nul("Repository ID", e))?;
+        Ok(RfcRepository {
+            rfc_lib,
+            id,
+            types: HashMap::new(),
+            functions: HashMap::new(),
+        })
+    }
+
+    /// Add a structure type. The field offsets are computed in declaration
+    /// order, with each field aligned to its natural alignment.
+    pub fn add_type(&mut self, name: &str, fields: &[FieldSpec]) -> Result<(), RfcErrorInfo> {
+        let api = &self.rfc_lib.rfc_api;
+        let name_uc = encode_name(name)?;
+        let mut err_trunk = RfcErrorInfo::new();
+        let handle = unsafe { api.RfcCreateTypeDesc(name_uc.as_ptr(), &mut err_trunk) };
+        if handle.is_null() {
+            return Err(err_trunk);
+        }
+        let res = self.fill_type(handle, fields);
+        let (nuc_align, uc_align) = match res {
+            Ok(align) => align,
+            Err(e) => {
+                unsafe { api.RfcDestroyTypeDesc(handle, &mut err_trunk) };
+                return Err(e);
+            }
+        };
+        let res = unsafe { api.RfcAddTypeDesc(self.id.as_ptr(), handle, &mut err_trunk) };
+        if !res.is_ok() {
+            unsafe { api.RfcDestroyTypeDesc(handle, &mut RfcErrorInfo::new()) };
+            return Err(err_trunk);
+        }
+        let entry = TypeEntry { handle, nuc_align, uc_align };
+        self.types.insert(name.to_uppercase(), entry);
+        Ok(())
+    }
+
+    /// Add the fields to a type and set its length. Returns the alignment
+    /// of the type in the non-unicode and the unicode layout.
+    fn fill_type(&self, handle: *mut RfcDataContainerHandle, fields: &[FieldSpec]) -> Result<(u32, u32), RfcErrorInfo> {
+        let api = &self.rfc_lib.rfc_api;
+        let (mut nuc_offset, mut uc_offset) = (0, 0);
+        let (mut nuc_max, mut uc_max) = (1, 1);
+        for field in fields {
+            let (type_desc_handle, nuc_align, uc_align) = match field.type_name {
+                Some(type_name) => {
+                    let entry = self.get_type(type_name)?;
+                    (entry.handle, entry.nuc_align, entry.uc_align)
+                }
+                None => (std::ptr::null_mut(), alignment(field.field_type, false), alignment(field.field_type, true)),
+            };
+            nuc_offset = align_to(nuc_offset, nuc_align);
+            uc_offset = align_to(uc_offset, uc_align);
+            nuc_max = nuc_max.max(nuc_align);
+            uc_max = uc_max.max(uc_align);
+
+            let mut desc = RfcFieldDesc::new(api);
+            desc.name = encode_name_buf(field.name)?;
+            desc.field_type = field.field_type.to_raw();
+            desc.nuc_length = field.nuc_length;
+            desc.nuc_offset = nuc_offset;
+            desc.uc_length = field.uc_length;
+            desc.uc_offset = uc_offset;
+            desc.decimals = field.decimals;
+            desc.type_desc_handle = type_desc_handle;
+            let mut err_trunk = RfcErrorInfo::new();
+            let res = unsafe { api.RfcAddTypeField(handle, &desc, &mut err_trunk) };
+            if !res.is_ok() {
+                return Err(err_trunk);
+            }
+            nuc_offset += field.nuc_length;
+            uc_offset += field.uc_length;
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            api.RfcSetTypeLength(handle, align_to(nuc_offset, nuc_max), align_to(uc_offset, uc_max), &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok((nuc_max, uc_max))
+    }
+
+    /// Add a function description
+    pub fn add_function(&mut self, name: &str, parameters: &[ParameterSpec]) -> Result<(), RfcErrorInfo> {
+        let api = &self.rfc_lib.rfc_api;
+        let name_uc = encode_name(name)?;
+        let mut err_trunk = RfcErrorInfo::new();
+        let fd = unsafe { api.RfcCreateFunctionDesc(name_uc.as_ptr(), &mut err_trunk) };
+        if fd.is_null() {
+            return Err(err_trunk);
+        }
+        for p in parameters {
+            let desc = match self.parameter_desc(p) {
+                Ok(desc) => desc,
+                Err(e) => {
+                    unsafe { api.RfcDestroyFunctionDesc(fd, &mut err_trunk) };
+                    return Err(e);
+                }
+            };
+            let res = unsafe { api.RfcAddParameter(fd, &desc, &mut err_trunk) };
+            if !res.is_ok() {
+                unsafe { api.RfcDestroyFunctionDesc(fd, &mut RfcErrorInfo::new()) };
+                return Err(err_trunk);
+            }
+        }
+        let res = unsafe { api.RfcAddFunctionDesc(self.id.as_ptr(), fd, &mut err_trunk) };
+        if !res.is_ok() {
+            unsafe { api.RfcDestroyFunctionDesc(fd, &mut RfcErrorInfo::new()) };
+            return Err(err_trunk);
+        }
+        let parameters = parameter_descs(self.rfc_lib, fd)?;
+        // SAFETY: fd was handed over to the metadata cache above
+        let desc = unsafe { FunctionDesc::from_cache(fd, parameters) };
+        self.functions.insert(name.to_uppercase(), desc);
+        Ok(())
+    }
+
+    fn parameter_desc(&self, p: &ParameterSpec) -> Result<RfcParameterDesc, RfcErrorInfo> {
+        let mut desc = RfcParameterDesc::new();
+        desc.name = encode_name_buf(p.name)?;
+        desc.field_type = p.field_type.to_raw();
+        desc.direction = p.direction;
+        desc.nuc_length = p.nuc_length;
+        desc.uc_length = p.uc_length;
+        desc.decimals = p.decimals;
+        desc.optional = p.optional as u8;
+        if let Some(type_name) = p.type_name {
+            desc.type_desc_handle = self.get_type(type_name)?.handle;
+        }
+        Ok(desc)
+    }
+
+    fn get_type(&self, name: &str) -> Result<&TypeEntry, RfcErrorInfo> {
+        self.types
+            .get(&name.to_uppercase())
+            .ok_or_else(|| RfcErrorInfo::custom(&format!("Unknown type {} in repository", name)))
+    }
+
+    /// The signature of a function added to the repository
+    pub fn describe_function(&self, name: &str) -> Result<FunctionMetadata, RfcErrorInfo> {
+        let desc = self.function_desc(name)?;
+        Ok(FunctionMetadata {
+            name: name.to_uppercase(),
+            parameters: desc.parameters.iter().map(ParameterMetadata::from_desc).collect(),
+        })
+    }
+
+    /// Create a function added to the repository without a connection,
+    /// e.g. to fill and read its parameters in tests. The function cannot
+    /// be called; use use_repository and RfcConnection::get_function for that.
+    pub fn get_function(&self, name: &str) -> Result<RfcFunction<'lib, 'lib>, RfcErrorInfo> {
+        let desc = self.function_desc(name)?.clone();
+        RfcFunction::new(self.rfc_lib, None, name, desc)
+    }
+
+    fn function_desc(&self, name: &str) -> Result<&FunctionDesc, RfcErrorInfo> {
+        self.functions
+            .get(&name.to_uppercase())
+            .ok_or_else(|| RfcErrorInfo::custom(&format!("Unknown function {} in repository", name)))
+    }
+}
+
+impl<'rfclib> RfcConnection<'rfclib> {
+    /// Resolve get_function and describe_function for the functions of
+    /// `repository` without looking them up in the SAP system
+    pub fn use_repository(&self, repository: &RfcRepository<'rfclib>) {
+        let mut cache = self.function_cache.borrow_mut();
+        for (name, desc) in &repository.functions {
+            cache.insert(name.clone(), desc.clone());
+        }
+    }
+}
+
+/// Encode a type, function, field or parameter name
+fn encode_name(name: &str) -> Result<U16CString, RfcErrorInfo> {
+    U16CString::from_str(name.to_uppercase()).map_err(|e| interior_nul(name, e))
+}
+
+/// Encode a name into the fixed size buffer of the RFC library
+fn encode_name_buf(name: &str) -> Result<[u16; 31], RfcErrorInfo> {
+    let name_uc = encode_name(name)?;
+    if name_uc.len() > 30 {
+        return Err(RfcErrorInfo::custom(&format!("Name {} is longer than 30 characters", name)));
+    }
+    let mut buf = [0u16; 31];
+    buf[..name_uc.len()].copy_from_slice(name_uc.as_slice());
+    Ok(buf)
+}
+
+/// Natural alignment of a field in the unicode or non-unicode layout
+fn alignment(field_type: RfcType, unicode: bool) -> u32 {
+    match field_type {
+        RfcType::Char | RfcType::Num | RfcType::Date | RfcType::Time | RfcType::UtcLong if unicode => 2,
+        RfcType::Int2 => 2,
+        RfcType::Int => 4,
+        RfcType::Float
+        | RfcType::Int8
+        | RfcType::Decf16
+        | RfcType::Decf34
+        | RfcType::String
+        | RfcType::XString
+        | RfcType::Table
+        | RfcType::Structure => 8,
+        _ => 1,
+    }
+}
+
+fn align_to(offset: u32, align: u32) -> u32 {
+    offset.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_field(name: &str, width: u32) -> FieldSpec<'_> {
+        FieldSpec {
+            name,
+            field_type: RfcType::Char,
+            nuc_length: width,
+            uc_length: 2 * width,
+            decimals: 0,
+            type_name: None,
+        }
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn field_count_of_structure_and_table() {
+        let lib = RfcLib::new().expect("libsapnwrfc not found");
+        let mut repository = RfcRepository::new(&lib, "RSRFC_TEST").unwrap();
+        repository
+            .add_type("ZRSRFC_USER", &[char_field("MANDT", 3), char_field("BNAME", 12)])
+            .unwrap();
+        let parameter = |name, field_type| ParameterSpec {
+            name,
+            field_type,
+            direction: RfcDirection::RfcChanging,
+            nuc_length: 15,
+            uc_length: 30,
+            decimals: 0,
+            optional: false,
+            type_name: Some("ZRSRFC_USER"),
+        };
+        repository
+            .add_function(
+                "Z_RSRFC_FIELD_COUNT",
+                &[parameter("USER", RfcType::Structure), parameter("USERS", RfcType::Table)],
+            )
+            .unwrap();
+        let mut fun = repository.get_function("Z_RSRFC_FIELD_COUNT").unwrap();
+        assert_eq!(fun.get_mut_parameter("USER").unwrap().get_field_count().unwrap(), 2);
+        assert_eq!(fun.get_mut_parameter("USERS").unwrap().get_field_count().unwrap(), 2);
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn recursive_type_is_rejected() {
+        let lib = RfcLib::new().expect("libsapnwrfc not found");
+        let api = &lib.rfc_api;
+        let mut repository = RfcRepository::new(&lib, "RSRFC_TEST").unwrap();
+        let id = FieldSpec {
+            name: "ID",
+            field_type: RfcType::Int,
+            nuc_length: 4,
+            uc_length: 4,
+            decimals: 0,
+            type_name: None,
+        };
+        repository.add_type("ZRSRFC_NODE", &[id]).unwrap();
+
+        // add_type only refers to types added before, so link the type to
+        // itself by hand
+        let handle = repository.get_type("ZRSRFC_NODE").unwrap().handle;
+        let mut next = RfcFieldDesc::new(api);
+        next.name = encode_name_buf("NEXT").unwrap();
+        next.field_type = RfcType::Structure.to_raw();
+        next.nuc_length = 8;
+        next.nuc_offset = 8;
+        next.uc_length = 8;
+        next.uc_offset = 8;
+        next.type_desc_handle = handle;
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { api.RfcAddTypeField(handle, &next, &mut err_trunk) };
+        assert!(res.is_ok(), "{}", err_trunk.message());
+        let res = unsafe { api.RfcSetTypeLength(handle, 16, 16, &mut err_trunk) };
+        assert!(res.is_ok(), "{}", err_trunk.message());
+
+        let node = ParameterSpec {
+            name: "NODE",
+            field_type: RfcType::Structure,
+            direction: RfcDirection::RfcImport,
+            nuc_length: 16,
+            uc_length: 16,
+            decimals: 0,
+            optional: false,
+            type_name: Some("ZRSRFC_NODE"),
+        };
+        repository.add_function("Z_RSRFC_RECURSIVE", &[node]).unwrap();
+        match repository.get_function("Z_RSRFC_RECURSIVE") {
+            Ok(_) => panic!("recursive type was accepted"),
+            Err(e) => assert_eq!(e.message(), "Recursive structure definition"),
+        };
+    }
+}
@@ -1,16 +1,110 @@
+// The members of RfcApi carry the names of the NW RFC library functions,
+// and the methods WrapperApi derives from them are not covered by an allow
+// on the struct.
+#![allow(non_snake_case)]
+
 use dlopen::wrapper::{Container, WrapperApi};
 use std::marker::PhantomData;
+use std::rc::Rc;
 use std::ptr::null_mut;
 
+use std::cell::Cell;
 use std::cmp::min;
-use widestring::U16CString;
+use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet};
+use widestring::{U16CString, U16String};
+
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
+#[cfg(feature = "decimal")]
+use std::str::FromStr;
+
+#[cfg(feature = "chrono")]
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 
 use crate::error::*;
+use crate::RfcValue;
 
 pub enum RfcFunctionDescHandle {}
 pub enum RfcConnectionHandle {}
 pub enum RfcDataContainerHandle {}
 pub enum RfcExtendedDescription {}
+pub enum RfcTransactionHandle {}
+
+/// A function descriptor together with its decoded parameter descriptions.
+/// Descriptors belong to the metadata cache of the loaded RFC library and
+/// must never be destroyed, so this type has no Drop and only hands out the
+/// raw handle for calls into the library.
+///
+/// It does not borrow the library: it is kept in the descriptor cache of
+/// RfcConnection, and a lifetime in there would make the connection
+/// invariant in the lifetime of the library. The connections and
+/// repositories holding a FunctionDesc borrow the library instead.
+#[derive(Clone)]
+pub(crate) struct FunctionDesc {
+    handle: *mut RfcFunctionDescHandle,
+    pub(crate) parameters: Vec<RfcParameterDesc>,
+}
+
+impl FunctionDesc {
+    /// Wrap a descriptor owned by the metadata cache of the RFC library.
+    ///
+    /// # Safety
+    /// `handle` must come from RfcGetFunctionDesc, or have been handed over
+    /// to the metadata cache with RfcAddFunctionDesc.
+    pub(crate) unsafe fn from_cache(handle: *mut RfcFunctionDescHandle, parameters: Vec<RfcParameterDesc>) -> FunctionDesc {
+        FunctionDesc { handle, parameters }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut RfcFunctionDescHandle {
+        self.handle
+    }
+}
+
+/// Attributes of an RFC connection, RFC library internal structure
+#[repr(C)]
+pub struct RfcAttributes {
+    pub dest: [u16; 65],
+    pub host: [u16; 101],
+    pub partner_host: [u16; 101],
+    pub sys_number: [u16; 3],
+    pub sys_id: [u16; 9],
+    pub client: [u16; 4],
+    pub user: [u16; 13],
+    pub language: [u16; 3],
+    pub trace: [u16; 2],
+    pub iso_language: [u16; 3],
+    pub codepage: [u16; 5],
+    pub partner_codepage: [u16; 5],
+    pub rfc_role: [u16; 2],
+    pub conn_type: [u16; 2],
+    pub partner_type: [u16; 2],
+    pub rel: [u16; 5],
+    pub partner_rel: [u16; 5],
+    pub kernel_rel: [u16; 5],
+    pub cpic_conv_id: [u16; 9],
+    pub prog_name: [u16; 129],
+    pub partner_bytes_per_char: [u16; 2],
+    pub partner_system_codepage: [u16; 5],
+    pub partner_ip: [u16; 16],
+    pub partner_ipv6: [u16; 46],
+    pub reserved: [u16; 17],
+}
+
+/// Callback of the RFC library asking for the description of a function
+/// that was called by the SAP system
+pub type RfcFuncDescCallback = unsafe extern "C" fn(
+    function_name: *const u16,
+    attributes: RfcAttributes,
+    fd: *mut *mut RfcFunctionDescHandle,
+) -> RfcRc;
+
+/// Callback of the RFC library for a function called by the SAP system
+pub type RfcServerFunction = unsafe extern "C" fn(
+    conn: *mut RfcConnectionHandle,
+    fun: *mut RfcDataContainerHandle,
+    error: *mut RfcErrorInfo,
+) -> RfcRc;
 
 /// Parameters specifying the RFC connection details
 #[repr(C)]
@@ -19,43 +113,118 @@ pub struct RfcConnectionParameter {
     pub value: *const u16,
 }
 
-/// RFC data type
-#[repr(u32)]
+/// RFC data type. Type codes unknown to this crate, e.g. from a newer
+/// RFC library, are kept as Unknown.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum RfcType {
-    Char = 0,
-    Date = 1,
-    Bcd = 2,
-    Time = 3,
-    Byte = 4,
-    Table = 5,
-    Num = 6,
-    Float = 7,
-    Int = 8,
-    Int2 = 9,
-    Int1 = 10,
-    Null = 14,
-    AbapObject = 16,
-    Structure = 17,
-    Decf16 = 23,
-    Decf34 = 24,
-    XmlData = 28,
-    String = 29,
-    XString = 30,
-    Int8 = 31,
-    UtcLong = 32,
-    UtcSecond = 33,
-    UtcMinute = 34,
-    DtDay = 35,
-    DtMonth = 36,
-    TSecond = 37,
-    TMinute = 38,
-    CDay = 39,
-    Box = 40,
-    GenericBox = 41,
+    Char,
+    Date,
+    Bcd,
+    Time,
+    Byte,
+    Table,
+    Num,
+    Float,
+    Int,
+    Int2,
+    Int1,
+    Null,
+    AbapObject,
+    Structure,
+    Decf16,
+    Decf34,
+    XmlData,
+    String,
+    XString,
+    Int8,
+    UtcLong,
+    UtcSecond,
+    UtcMinute,
+    DtDay,
+    DtMonth,
+    TSecond,
+    TMinute,
+    CDay,
+    Box,
+    GenericBox,
+    Unknown(u32),
 }
 
 impl RfcType {
+    /// Map a type code of the RFC library to an RfcType
+    pub fn from_raw(code: u32) -> RfcType {
+        match code {
+            0 => RfcType::Char,
+            1 => RfcType::Date,
+            2 => RfcType::Bcd,
+            3 => RfcType::Time,
+            4 => RfcType::Byte,
+            5 => RfcType::Table,
+            6 => RfcType::Num,
+            7 => RfcType::Float,
+            8 => RfcType::Int,
+            9 => RfcType::Int2,
+            10 => RfcType::Int1,
+            14 => RfcType::Null,
+            16 => RfcType::AbapObject,
+            17 => RfcType::Structure,
+            23 => RfcType::Decf16,
+            24 => RfcType::Decf34,
+            28 => RfcType::XmlData,
+            29 => RfcType::String,
+            30 => RfcType::XString,
+            31 => RfcType::Int8,
+            32 => RfcType::UtcLong,
+            33 => RfcType::UtcSecond,
+            34 => RfcType::UtcMinute,
+            35 => RfcType::DtDay,
+            36 => RfcType::DtMonth,
+            37 => RfcType::TSecond,
+            38 => RfcType::TMinute,
+            39 => RfcType::CDay,
+            40 => RfcType::Box,
+            41 => RfcType::GenericBox,
+            _ => RfcType::Unknown(code),
+        }
+    }
+
+    /// The type code of the RFC library
+    pub fn to_raw(&self) -> u32 {
+        match self {
+            RfcType::Char => 0,
+            RfcType::Date => 1,
+            RfcType::Bcd => 2,
+            RfcType::Time => 3,
+            RfcType::Byte => 4,
+            RfcType::Table => 5,
+            RfcType::Num => 6,
+            RfcType::Float => 7,
+            RfcType::Int => 8,
+            RfcType::Int2 => 9,
+            RfcType::Int1 => 10,
+            RfcType::Null => 14,
+            RfcType::AbapObject => 16,
+            RfcType::Structure => 17,
+            RfcType::Decf16 => 23,
+            RfcType::Decf34 => 24,
+            RfcType::XmlData => 28,
+            RfcType::String => 29,
+            RfcType::XString => 30,
+            RfcType::Int8 => 31,
+            RfcType::UtcLong => 32,
+            RfcType::UtcSecond => 33,
+            RfcType::UtcMinute => 34,
+            RfcType::DtDay => 35,
+            RfcType::DtMonth => 36,
+            RfcType::TSecond => 37,
+            RfcType::TMinute => 38,
+            RfcType::CDay => 39,
+            RfcType::Box => 40,
+            RfcType::GenericBox => 41,
+            RfcType::Unknown(code) => *code,
+        }
+    }
+
     /// Return true if the RFC type is a table or a struct.
     /// (A table is a list of structs, if you will)
     pub fn is_struct_or_table(&self) -> bool {
@@ -92,6 +261,46 @@ impl RfcType {
     }
 }
 
+/// Formats the type with its ABAP Dictionary name, e.g. CHAR, P or DATS
+impl std::fmt::Display for RfcType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            RfcType::Char => "CHAR",
+            RfcType::Date => "DATS",
+            RfcType::Bcd => "P",
+            RfcType::Time => "TIMS",
+            RfcType::Byte => "RAW",
+            RfcType::Table => "TABLE",
+            RfcType::Num => "NUMC",
+            RfcType::Float => "FLTP",
+            RfcType::Int => "INT4",
+            RfcType::Int2 => "INT2",
+            RfcType::Int1 => "INT1",
+            RfcType::Null => "NULL",
+            RfcType::AbapObject => "OBJECT",
+            RfcType::Structure => "STRUCTURE",
+            RfcType::Decf16 => "DECFLOAT16",
+            RfcType::Decf34 => "DECFLOAT34",
+            RfcType::XmlData => "XMLDATA",
+            RfcType::String => "STRING",
+            RfcType::XString => "RAWSTRING",
+            RfcType::Int8 => "INT8",
+            RfcType::UtcLong => "UTCLONG",
+            RfcType::UtcSecond => "UTCSECOND",
+            RfcType::UtcMinute => "UTCMINUTE",
+            RfcType::DtDay => "DTDAY",
+            RfcType::DtMonth => "DTMONTH",
+            RfcType::TSecond => "TSECOND",
+            RfcType::TMinute => "TMINUTE",
+            RfcType::CDay => "CDAY",
+            RfcType::Box => "BOX",
+            RfcType::GenericBox => "GENERIC_BOX",
+            RfcType::Unknown(code) => return write!(f, "UNKNOWN({})", code),
+        };
+        f.write_str(name)
+    }
+}
+
 /// RFC enabled functions can take different kinds of parameters.
 /// This enum specified the kind.
 #[repr(u32)]
@@ -133,14 +342,15 @@ impl RfcDirection {
 /// Internal RFC lib structure describing one RFC parameter.
 #[repr(C)]
 pub struct RfcFieldDesc<'conn> {
-    name: [u16; 31],
-    field_type: RfcType,
-    nuc_length: u32,
-    nuc_offset: u32,
-    uc_length: u32,
-    uc_offset: u32,
-    decimals: u32,
-    type_desc_handle: *mut RfcDataContainerHandle,
+    pub(crate) name: [u16; 31],
+    /// Raw type code, see RfcType::from_raw
+    pub(crate) field_type: u32,
+    pub(crate) nuc_length: u32,
+    pub(crate) nuc_offset: u32,
+    pub(crate) uc_length: u32,
+    pub(crate) uc_offset: u32,
+    pub(crate) decimals: u32,
+    pub(crate) type_desc_handle: *mut RfcDataContainerHandle,
     extended_description: *mut RfcExtendedDescription,
     rfc_api: &'conn Container<RfcApi>
 }
@@ -150,7 +360,7 @@ impl <'conn> RfcFieldDesc<'conn> {
     pub fn new(rfc_api: &'conn Container<RfcApi>) -> RfcFieldDesc<'conn> {
         RfcFieldDesc {
             name: [0 as u16; 31],
-            field_type: RfcType::String,
+            field_type: 0,
             nuc_length: 0,
             nuc_offset: 0,
             uc_length: 0,
@@ -162,24 +372,19 @@ impl <'conn> RfcFieldDesc<'conn> {
         }
     }
 
-    /// Convert to an RFC parameter
+    /// Convert to an RFC parameter. `visited` holds the type handles of the
+    /// enclosing structures to detect recursive type definitions.
     pub fn to_parameter<'strct: 'conn>(
         &self,
         index: u32,
         fun: *mut RfcDataContainerHandle,
+        visited: &mut HashSet<usize>,
     ) -> Result<RfcParameter<'conn, 'strct>, RfcErrorInfo> {
-        let name_s = unsafe { U16CString::from_ptr_with_nul(self.name.as_ptr(), 31) };
-        if let Err(e) = name_s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
-        }
-        let name_s = name_s.unwrap().to_string();
-        if let Err(e) = name_s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
-        }
-        let name_s = name_s.unwrap();
+        let name_s = decode_name(&self.name, "field name")?;
 
+        let field_type = RfcType::from_raw(self.field_type);
         let mut structure_or_table = null_mut();
-        if &self.field_type == &RfcType::Structure {
+        if field_type == RfcType::Structure {
             let mut err_trunk = RfcErrorInfo::new();
             let res = unsafe {
                 self.rfc_api. RfcGetStructureByIndex(fun, index, &mut structure_or_table, &mut err_trunk)
@@ -187,7 +392,7 @@ impl <'conn> RfcFieldDesc<'conn> {
             if !res.is_ok() {
                 return Err(err_trunk);
             }
-        } else if &self.field_type == &RfcType::Table {
+        } else if field_type == RfcType::Table {
             let mut err_trunk = RfcErrorInfo::new();
             let res =
                 unsafe { self.rfc_api.RfcGetTableByIndex(fun, index, &mut structure_or_table, &mut err_trunk) };
@@ -199,19 +404,25 @@ impl <'conn> RfcFieldDesc<'conn> {
         let struct_def = if structure_or_table.is_null() {
             None
         } else {
-            let res = RfcDecodedFieldDesc::from_handle(self.rfc_api,structure_or_table)?;
+            let res = RfcDecodedFieldDesc::from_handle_visited(self.rfc_api, structure_or_table, visited)?;
             Some(res)
         };
 
         Ok(RfcParameter {
             index,
             name: name_s,
-            field_type: self.field_type,
+            field_type,
             direction: RfcDirection::RfcChanging,
             len: self.uc_length,
+            nuc_len: self.nuc_length,
+            decimals: self.decimals,
             struct_def,
             default_value: None,
+            description: None,
             optional: false,
+            written: false,
+            row_count: Cell::new(None),
+            call_state: None,
             fun,
             structure_or_table,
             p1: PhantomData,
@@ -221,6 +432,14 @@ impl <'conn> RfcFieldDesc<'conn> {
     }
 }
 
+/// Shared between a function and its EXPORTING parameters to detect
+/// reads before the function was called, see RfcFunction::strict
+#[derive(Default)]
+pub(crate) struct CallState {
+    pub(crate) strict: Cell<bool>,
+    pub(crate) called: Cell<bool>,
+}
+
 /// Decoded RFC field description
 pub struct RfcDecodedFieldDesc<'conn, 'strct: 'conn> {
     pub fields: Vec<RfcDecodedField<'conn, 'strct>>,
@@ -229,9 +448,11 @@ pub struct RfcDecodedFieldDesc<'conn, 'strct: 'conn> {
 
 /// An RFC parameter description, RFC library internal structure
 #[repr(C)]
-pub struct RfcParameterDesc<'conn> {
+#[derive(Clone)]
+pub struct RfcParameterDesc {
     pub name: [u16; 31],
-    pub field_type: RfcType,
+    /// Raw type code, see RfcType::from_raw
+    pub field_type: u32,
     pub direction: RfcDirection,
     pub nuc_length: u32,
     pub uc_length: u32,
@@ -241,14 +462,13 @@ pub struct RfcParameterDesc<'conn> {
     pub parameter_text: [u16; 80],
     pub optional: u8,
     pub extended_description: *mut u8,
-    rfc_api: &'conn Container<RfcApi>,
 }
 
-impl <'conn>RfcParameterDesc<'conn> {
-    pub fn new(rfc_api: &'conn Container<RfcApi>) -> RfcParameterDesc<'conn> {
+impl RfcParameterDesc {
+    pub fn new() -> RfcParameterDesc {
         RfcParameterDesc {
             name: [0 as u16; 31],
-            field_type: RfcType::String,
+            field_type: 0,
             direction: RfcDirection::RfcExport,
             nuc_length: 0,
             uc_length: 0,
@@ -256,55 +476,41 @@ impl <'conn>RfcParameterDesc<'conn> {
             type_desc_handle: null_mut(),
             default_value: [0 as u16; 31],
             parameter_text: [0 as u16; 80],
-            optional: 0 as u8,
+            optional: 0,
             extended_description: null_mut(),
-            rfc_api
         }
     }
 
-    pub fn to_parameter<'strct: 'conn>(
+    pub fn to_parameter<'conn, 'strct: 'conn>(
         &self,
+        rfc_api: &'conn Container<RfcApi>,
         index: u32,
         fun: *mut RfcDataContainerHandle,
     ) -> Result<RfcParameter<'conn, 'strct>, RfcErrorInfo> {
-        let name_s = unsafe { U16CString::from_ptr_with_nul(self.name.as_ptr(), 31) };
-        if let Err(e) = name_s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
-        }
-        let name_s = name_s.unwrap().to_string();
-        if let Err(e) = name_s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
-        }
-        let name_s = name_s.unwrap();
+        let name_s = decode_name(&self.name, "parameter name")?;
 
-        let default_value = if self.default_value[0] == 0 {
-            None
-        } else {
-            let default_value_s =
-                unsafe { U16CString::from_ptr_with_nul(self.default_value.as_ptr(), 31) };
-            if let Err(e) = default_value_s {
-                return Err(RfcErrorInfo::custom(&e.to_string()));
-            }
-            let default_value_s = default_value_s.unwrap().to_string();
-            if let Err(e) = default_value_s {
-                return Err(RfcErrorInfo::custom(&e.to_string()));
-            }
-            Some(default_value_s.unwrap())
-        };
+        // Like the description, the default value is only informational, so
+        // it is decoded leniently instead of failing the whole function
+        let default_value = decode_buf(&self.default_value);
+        let default_value = if default_value.is_empty() { None } else { Some(default_value) };
+
+        let description = decode_buf(&self.parameter_text);
+        let description = if description.is_empty() { None } else { Some(description) };
 
+        let field_type = RfcType::from_raw(self.field_type);
         let mut structure_or_table = null_mut();
-        if &self.field_type == &RfcType::Structure {
+        if field_type == RfcType::Structure {
             let mut err_trunk = RfcErrorInfo::new();
             let res = unsafe {
-                self.rfc_api. RfcGetStructureByIndex(fun, index, &mut structure_or_table, &mut err_trunk)
+                rfc_api.RfcGetStructureByIndex(fun, index, &mut structure_or_table, &mut err_trunk)
             };
             if !res.is_ok() {
                 return Err(err_trunk);
             }
-        } else if &self.field_type == &RfcType::Table {
+        } else if field_type == RfcType::Table {
             let mut err_trunk = RfcErrorInfo::new();
             let res =
-                unsafe { self.rfc_api.RfcGetTableByIndex(fun, index, &mut structure_or_table, &mut err_trunk) };
+                unsafe { rfc_api.RfcGetTableByIndex(fun, index, &mut structure_or_table, &mut err_trunk) };
             if !res.is_ok() {
                 return Err(err_trunk);
             }
@@ -313,24 +519,30 @@ impl <'conn>RfcParameterDesc<'conn> {
         let struct_def = if structure_or_table.is_null() {
             None
         } else {
-            let res = RfcDecodedFieldDesc::from_handle(self.rfc_api,structure_or_table)?;
+            let res = RfcDecodedFieldDesc::from_handle(rfc_api, structure_or_table)?;
             Some(res)
         };
 
         Ok(RfcParameter {
             index,
             name: name_s,
-            field_type: self.field_type,
+            field_type,
             direction: self.direction,
             len: self.uc_length,
+            nuc_len: self.nuc_length,
+            decimals: self.decimals,
             struct_def,
             default_value,
+            description,
             optional: self.optional != 0,
+            written: false,
+            row_count: Cell::new(None),
+            call_state: None,
             fun,
             structure_or_table,
             p1: PhantomData,
             p2: PhantomData,
-            rfc_api: self.rfc_api,
+            rfc_api,
         })
     }
 }
@@ -342,9 +554,20 @@ pub struct RfcParameter<'conn, 'strct: 'conn> {
     pub field_type: RfcType,
     pub direction: RfcDirection,
     pub len: u32,
-    struct_def: Option<RfcDecodedFieldDesc<'conn, 'strct>>,
+    /// Length in bytes in the non-unicode layout, see nuc_len
+    nuc_len: u32,
+    /// Number of decimal places, for BCD and decimal float values
+    pub decimals: u32,
+    pub(crate) struct_def: Option<RfcDecodedFieldDesc<'conn, 'strct>>,
     default_value: Option<String>,
+    description: Option<String>,
     optional: bool,
+    /// Set once any set_* method succeeded on this parameter
+    written: bool,
+    /// Call state of the function, for EXPORTING parameters and their fields
+    call_state: Option<Rc<CallState>>,
+    /// Cached number of rows of a table, see row_count
+    row_count: Cell<Option<u32>>,
     fun: *mut RfcDataContainerHandle,
     structure_or_table: *mut RfcDataContainerHandle,
     p1: PhantomData<&'conn RfcConnectionHandle>,
@@ -352,18 +575,80 @@ pub struct RfcParameter<'conn, 'strct: 'conn> {
     rfc_api: &'conn Container<RfcApi>
 }
 
+/// Only shows the description of the parameter, not its handles or fields
+impl<'conn, 'strct: 'conn> std::fmt::Debug for RfcParameter<'conn, 'strct> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RfcParameter")
+            .field("name", &self.name)
+            .field("field_type", &self.field_type)
+            .field("direction", &self.direction)
+            .field("len", &self.len)
+            .field("optional", &self.optional)
+            .finish()
+    }
+}
+
 impl<'conn, 'strct: 'conn> RfcDecodedFieldDesc<'conn, 'strct> {
+    /// Iterate over the leaf fields of the structure, recursing into nested
+    /// structures and tables. Each field comes with its dotted path, e.g.
+    /// ITEM.MATNR.
+    pub fn iter_flat(&self) -> impl Iterator<Item = (String, &RfcDecodedField<'conn, 'strct>)> {
+        let mut leaves = Vec::new();
+        self.collect_leaves("", &mut leaves);
+        leaves.into_iter()
+    }
+
+    fn collect_leaves<'a>(&'a self, prefix: &str, leaves: &mut Vec<(String, &'a RfcDecodedField<'conn, 'strct>)>) {
+        for field in &self.fields {
+            let path = if prefix.is_empty() {
+                field.name.clone()
+            } else {
+                format!("{}.{}", prefix, field.name)
+            };
+            match &field.sub_fields {
+                Some(sub_fields) => sub_fields.collect_leaves(&path, leaves),
+                None => leaves.push((path, field)),
+            }
+        }
+    }
+
     pub fn from_handle(
         rfc_api: &'conn Container<RfcApi>,
         handle: *mut RfcDataContainerHandle,
     ) -> Result<RfcDecodedFieldDesc<'conn, 'strct>, RfcErrorInfo> {
-        let mut count: u32 = 0;
+        Self::from_handle_visited(rfc_api, handle, &mut HashSet::new())
+    }
+
+    /// from_handle, with the type handles of the enclosing structures in
+    /// `visited`. A type that contains itself, directly or through deeper
+    /// nesting, is reported as an error instead of recursing forever.
+    fn from_handle_visited(
+        rfc_api: &'conn Container<RfcApi>,
+        handle: *mut RfcDataContainerHandle,
+        visited: &mut HashSet<usize>,
+    ) -> Result<RfcDecodedFieldDesc<'conn, 'strct>, RfcErrorInfo> {
         let mut err_trunk = RfcErrorInfo::new();
 
         let type_handle = unsafe { rfc_api.RfcDescribeType(handle, &mut err_trunk) };
         if type_handle.is_null() {
             return Err(err_trunk);
         }
+        if !visited.insert(type_handle as usize) {
+            return Err(RfcErrorInfo::custom("Recursive structure definition"));
+        }
+        let res = Self::decode_type(rfc_api, handle, type_handle, visited);
+        visited.remove(&(type_handle as usize));
+        res
+    }
+
+    fn decode_type(
+        rfc_api: &'conn Container<RfcApi>,
+        handle: *mut RfcDataContainerHandle,
+        type_handle: *mut RfcDataContainerHandle,
+        visited: &mut HashSet<usize>,
+    ) -> Result<RfcDecodedFieldDesc<'conn, 'strct>, RfcErrorInfo> {
+        let mut count: u32 = 0;
+        let mut err_trunk = RfcErrorInfo::new();
 
         {
             let res = unsafe { rfc_api.RfcGetFieldCount(type_handle, &mut count, &mut err_trunk) };
@@ -379,26 +664,22 @@ impl<'conn, 'strct: 'conn> RfcDecodedFieldDesc<'conn, 'strct> {
                 let res = unsafe {
                     rfc_api.RfcGetFieldDescByIndex(type_handle, i, &mut rfc_field_desc, &mut err_trunk)
                 };
-                parameters.push(rfc_field_desc.to_parameter(i, handle)?);
                 if !res.is_ok() {
                     return Err(err_trunk);
                 }
-                let name = unsafe {
-                    U16CString::from_ptr_with_nul(rfc_field_desc.name.as_ptr(), 31)
-                        .unwrap()
-                        .to_string_lossy()
-                };
+                parameters.push(rfc_field_desc.to_parameter(i, handle, visited)?);
+                let name = decode_name(&rfc_field_desc.name, "field name")?;
                 let sub_fields = if rfc_field_desc.type_desc_handle.is_null() {
                     None
                 } else {
-                    let d = RfcDecodedFieldDesc::from_handle(rfc_api, rfc_field_desc.type_desc_handle)?;
+                    let d = RfcDecodedFieldDesc::from_handle_visited(rfc_api, rfc_field_desc.type_desc_handle, visited)?;
                     Some(Box::new(d))
                 };
                 let field = RfcDecodedField {
                     name,
                     index: i,
                     len: rfc_field_desc.uc_length,
-                    field_type: rfc_field_desc.field_type,
+                    field_type: RfcType::from_raw(rfc_field_desc.field_type),
                     sub_fields,
                     phantom: PhantomData,
                 };
@@ -410,6 +691,70 @@ impl<'conn, 'strct: 'conn> RfcDecodedFieldDesc<'conn, 'strct> {
     }
 }
 
+/// Iterator over the rows of a table parameter, see RfcParameter::rows
+pub struct RfcRows<'a, 'conn, 'strct: 'conn> {
+    table: &'a RfcParameter<'conn, 'strct>,
+    next: u32,
+    count: u32,
+}
+
+impl<'a, 'conn, 'strct: 'conn> Iterator for RfcRows<'a, 'conn, 'strct> {
+    type Item = RfcRow<'a, 'conn, 'strct>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.count {
+            return None;
+        }
+        let row = RfcRow {
+            table: self.table,
+            index: self.next,
+        };
+        self.next += 1;
+        Some(row)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+/// One row of a table parameter, as returned by RfcRows
+pub struct RfcRow<'a, 'conn, 'strct: 'conn> {
+    table: &'a RfcParameter<'conn, 'strct>,
+    index: u32,
+}
+
+impl<'a, 'conn, 'strct: 'conn> RfcRow<'a, 'conn, 'strct> {
+    /// The index of this row within the table
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Move the table cursor to this row and return the field with the
+    /// given name. The field reads from whatever row the cursor points to,
+    /// so use it before accessing another row.
+    pub fn get_field(&self, name: &str) -> Result<&'a RfcParameter<'conn, 'strct>, RfcErrorInfo> {
+        self.table.set_row(self.index)?;
+        self.table.get_field(name)
+    }
+
+    /// Get a CHAR field of this row
+    pub fn get_chars(&self, name: &str) -> Result<String, RfcErrorInfo> {
+        self.get_field(name)?.get_chars()
+    }
+
+    /// Get a STRING field of this row
+    pub fn get_string(&self, name: &str) -> Result<String, RfcErrorInfo> {
+        self.get_field(name)?.get_string()
+    }
+
+    /// Get an integer field of this row
+    pub fn get_int(&self, name: &str) -> Result<i64, RfcErrorInfo> {
+        self.get_field(name)?.get_int()
+    }
+}
+
 pub struct RfcDecodedField<'conn, 'strct: 'conn> {
     name: String,
     index: u32,
@@ -419,6 +764,35 @@ pub struct RfcDecodedField<'conn, 'strct: 'conn> {
     phantom: PhantomData<&'strct RfcDataContainerHandle>,
 }
 
+// len is the byte length of the field, there is no notion of being empty
+#[allow(clippy::len_without_is_empty)]
+impl<'conn, 'strct: 'conn> RfcDecodedField<'conn, 'strct> {
+    /// Name of the field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Position of the field within its structure
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Length of the field in bytes in the unicode layout
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// RFC type of the field
+    pub fn field_type(&self) -> RfcType {
+        self.field_type
+    }
+
+    /// Decoded fields of a nested structure or table field
+    pub fn sub_fields(&self) -> Option<&RfcDecodedFieldDesc<'conn, 'strct>> {
+        self.sub_fields.as_deref()
+    }
+}
+
 impl RfcErrorInfo {
     pub fn new() -> RfcErrorInfo {
         RfcErrorInfo {
@@ -438,23 +812,123 @@ impl RfcErrorInfo {
 
     pub fn custom(msg: &str) -> RfcErrorInfo {
         let mut err_trunk = RfcErrorInfo::new();
-        let msg_enc = U16CString::from_str(msg).unwrap();
-        let msg_enc = msg_enc.into_vec_with_nul();
-        let len = min(err_trunk.message.len(), msg_enc.len());
-        unsafe {
-            std::ptr::copy(msg_enc.as_ptr(), err_trunk.message.as_mut_ptr(), len);
-        }
-
+        err_trunk.set_message(msg);
         err_trunk.code = RfcRc::RfcCaiberp;
         err_trunk.group = RfcErrorGroup::CaiberP;
         err_trunk
     }
+
+    /// An error that an RFC server handler returns to raise the classic
+    /// exception `key` of the called function module
+    pub fn abap_exception(key: &str, msg: &str) -> RfcErrorInfo {
+        let mut err_trunk = RfcErrorInfo::custom(msg);
+        err_trunk.code = RfcRc::RfcAbapException;
+        err_trunk.group = RfcErrorGroup::AbapApplicationFailure;
+        let key_enc = U16String::from_str(key).into_vec();
+        let len = min(err_trunk.key.len() - 1, key_enc.len());
+        err_trunk.key[..len].copy_from_slice(&key_enc[..len]);
+        err_trunk
+    }
+
+    /// Replace the message text, truncating it to the size of the buffer
+    pub(crate) fn set_message(&mut self, msg: &str) {
+        let msg_enc = U16String::from_str(msg).into_vec();
+        let len = min(self.message.len() - 1, msg_enc.len());
+        self.message = [0; 512];
+        self.message[..len].copy_from_slice(&msg_enc[..len]);
+    }
 }
 
+/// Maximum number of rows RfcParameter::append_rows appends in one call
+pub const MAX_APPEND_ROWS: u32 = 1_000_000;
+
 impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
+    /// Width in characters of a fixed length character-like field (CHAR,
+    /// NUM, DATE, TIME). `len` is the length in bytes in the unicode layout,
+    /// which uses two bytes per character.
+    fn char_width(&self) -> u32 {
+        self.len / 2
+    }
+
+    /// Fail if the parameter cannot be read, or if it is an EXPORTING
+    /// parameter of a strict function that was not called yet
+    fn ensure_readable(&self) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_read() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if let Some(state) = &self.call_state {
+            if state.strict.get() && !state.called.get() {
+                return Err(RfcErrorInfo::custom(&format!(
+                    "Parameter {} is read before the function was called",
+                    self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Attach the call state of the function to this parameter and all of
+    /// its fields
+    pub(crate) fn set_call_state(&mut self, state: &Rc<CallState>) {
+        self.call_state = Some(Rc::clone(state));
+        if let Some(sd) = self.struct_def.as_mut() {
+            for field in sd.parameters.iter_mut() {
+                field.set_call_state(state);
+            }
+        }
+    }
+
+    /// Return true if the parameter does not have to be supplied by the caller
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Length in bytes in the unicode layout, the same as `len`
+    pub fn uc_len(&self) -> u32 {
+        self.len
+    }
+
+    /// Length in bytes in the non-unicode layout, e.g. to compute offsets
+    /// in data exchanged with a non-unicode system
+    pub fn nuc_len(&self) -> u32 {
+        self.nuc_len
+    }
+
+    /// Default value of an optional parameter as declared in the function
+    /// module, e.g. "SPACE" or "'X'"
+    pub fn default_value(&self) -> Option<&str> {
+        self.default_value.as_deref()
+    }
+
+    /// Short text of the parameter as maintained in the function module
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Return true if a value was set on this parameter, or on any field of
+    /// a structure or table parameter
+    pub fn was_written(&self) -> bool {
+        self.written
+            || self
+                .struct_def
+                .as_ref()
+                .is_some_and(|sd| sd.parameters.iter().any(|f| f.was_written()))
+    }
+
+    /// Append `count` empty rows to a table. At most MAX_APPEND_ROWS rows
+    /// can be appended at once; larger inserts have to be split into chunks.
     pub fn append_rows(&self, count: u32) -> Result<(), RfcErrorInfo> {
         self.field_type.ensure_table()?;
+        if count > MAX_APPEND_ROWS {
+            let mut err = RfcErrorInfo::custom(&format!(
+                "Cannot append {} rows to table {} at once, the limit is {}",
+                count, self.name, MAX_APPEND_ROWS
+            ));
+            err.code = RfcRc::RfcInvalidParameter;
+            return Err(err);
+        }
         let mut err_trunk = RfcErrorInfo::new();
+        self.forget_row_count();
         let res = unsafe { self.rfc_api.RfcAppendNewRows(self.structure_or_table, count, &mut err_trunk) };
         if res.is_ok() {
             Ok(())
@@ -463,6 +937,125 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
         }
     }
 
+    /// Like append_rows, but only return the RFC return code on failure.
+    /// RfcMemoryInsufficient means the rows could not be allocated and the
+    /// caller should retry with a smaller count; RfcInvalidParameter means
+    /// the count exceeded MAX_APPEND_ROWS.
+    pub fn try_append_rows(&self, count: u32) -> Result<(), RfcRc> {
+        self.append_rows(count).map_err(|e| e.code)
+    }
+
+    /// Set a field of a structure by name, see set_value. A flat structure
+    /// import parameter can be filled this way without any row handling;
+    /// for a table the field of the current row is set.
+    pub fn set_field(&mut self, name: &str, value: RfcValue) -> Result<(), RfcErrorInfo> {
+        self.get_field_mut(name)?.set_value(value)
+    }
+
+    /// Append a row to a table and set its fields from a map of field names
+    /// to values, see set_value. Field names are matched case insensitively
+    /// and all of them are checked before the row is appended.
+    pub fn append_row_from(&mut self, fields: &HashMap<&str, RfcValue>) -> Result<(), RfcErrorInfo> {
+        self.field_type.ensure_table()?;
+        for name in fields.keys() {
+            self.get_field(name)?;
+        }
+        self.append_rows(1)?;
+        self.last_row()?;
+        for (name, value) in fields {
+            self.get_field_mut(name)?.set_value(value.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Read all rows of a table into maps of field name to value, see
+    /// get_value. Trailing blanks of character fields are removed. Fails if
+    /// a row contains a nested structure or table.
+    pub fn read_table(&self) -> Result<Vec<HashMap<String, RfcValue>>, RfcErrorInfo> {
+        self.field_type.ensure_table()?;
+        let struct_def = self.struct_def_or_err()?;
+        let count = self.row_count()?;
+        let mut rows = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            self.set_row(i)?;
+            let mut row = HashMap::with_capacity(struct_def.parameters.len());
+            for field in &struct_def.parameters {
+                let value = match field.get_value()? {
+                    RfcValue::Chars(v) => RfcValue::Chars(v.trim_end().to_string()),
+                    v => v,
+                };
+                row.insert(field.name.clone(), value);
+            }
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Call `f` for each row of a table, in order, without reading the rows
+    /// into memory first. The cursor is moved to the first row before and to
+    /// the next row after each call, so `f` reads the current row through
+    /// the fields of this parameter. Stops at the first error of `f`.
+    pub fn for_each_row<F>(&self, mut f: F) -> Result<(), RfcErrorInfo>
+    where
+        F: FnMut(&RfcParameter<'conn, 'strct>) -> Result<(), RfcErrorInfo>,
+    {
+        self.field_type.ensure_table()?;
+        let count = self.row_count()?;
+        if count == 0 {
+            return Ok(());
+        }
+        self.first_row()?;
+        for i in 0..count {
+            if i > 0 {
+                self.next_row()?;
+            }
+            f(self)?;
+        }
+        Ok(())
+    }
+
+    /// Insert an empty row in front of the row the table cursor points to.
+    /// The cursor is moved to the new row, so subsequent field accesses
+    /// write into it.
+    pub fn insert_row(&self) -> Result<(), RfcErrorInfo> {
+        self.field_type.ensure_table()?;
+        let mut err_trunk = RfcErrorInfo::new();
+        self.forget_row_count();
+        let row = unsafe { self.rfc_api.RfcInsertNewRow(self.structure_or_table, &mut err_trunk) };
+        if row.is_null() {
+            Err(err_trunk)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Delete the row the table cursor points to
+    pub fn delete_current_row(&self) -> Result<(), RfcErrorInfo> {
+        self.field_type.ensure_table()?;
+        let mut err_trunk = RfcErrorInfo::new();
+        self.forget_row_count();
+        let res = unsafe { self.rfc_api.RfcDeleteCurrentRow(self.structure_or_table, &mut err_trunk) };
+        if res.is_ok() {
+            Ok(())
+        } else {
+            Err(err_trunk)
+        }
+    }
+
+    /// Delete all rows of the table, e.g. to reuse a function for
+    /// another call.
+    pub fn delete_all_rows(&self) -> Result<(), RfcErrorInfo> {
+        self.field_type.ensure_table()?;
+        let mut err_trunk = RfcErrorInfo::new();
+        self.forget_row_count();
+        let res = unsafe { self.rfc_api.RfcDeleteAllRows(self.structure_or_table, &mut err_trunk) };
+        if res.is_ok() {
+            Ok(())
+        } else {
+            Err(err_trunk)
+        }
+    }
+
     pub fn first_row(&self) -> Result<(), RfcErrorInfo> {
         self.field_type.ensure_table()?;
         let mut err_trunk = RfcErrorInfo::new();
@@ -518,6 +1111,32 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
         }
     }
 
+    /// Return the index of the row the table cursor points to, e.g. to
+    /// restore the position with set_row after a nested read. The RFC
+    /// library only reports the current row as a handle, so it is compared
+    /// with the handle of every row in turn; this takes time linear in the
+    /// number of rows.
+    pub fn get_current_row(&self) -> Result<u32, RfcErrorInfo> {
+        self.field_type.ensure_table()?;
+        let current = self.current_row_handle()?;
+        for i in 0..self.row_count()? {
+            self.set_row(i)?;
+            if self.current_row_handle()? == current {
+                return Ok(i);
+            }
+        }
+        Err(RfcErrorInfo::custom("The table cursor does not point to a row"))
+    }
+
+    fn current_row_handle(&self) -> Result<*mut RfcDataContainerHandle, RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let row = unsafe { self.rfc_api.RfcGetCurrentRow(self.structure_or_table, &mut err_trunk) };
+        if row.is_null() {
+            return Err(err_trunk);
+        }
+        Ok(row)
+    }
+
     pub fn get_row_count(&self) -> Result<u32, RfcErrorInfo> {
         self.field_type.ensure_table()?;
         let mut err_trunk = RfcErrorInfo::new();
@@ -531,68 +1150,273 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
         }
     }
 
-    pub fn get_field_count(&self) -> Result<u32, RfcErrorInfo> {
-        self.field_type.ensure_table()?;
+    /// Like get_row_count, but the result is cached until rows are added
+    /// or deleted through this parameter, or the function is called
+    pub fn row_count(&self) -> Result<u32, RfcErrorInfo> {
+        if let Some(count) = self.row_count.get() {
+            return Ok(count);
+        }
+        let count = self.get_row_count()?;
+        self.row_count.set(Some(count));
+        Ok(count)
+    }
+
+    /// Drop the cached row count of this parameter and of the tables
+    /// nested in its fields
+    pub(crate) fn forget_row_count(&self) {
+        self.row_count.set(None);
+        if let Some(sd) = self.struct_def.as_ref() {
+            for field in sd.parameters.iter() {
+                field.forget_row_count();
+            }
+        }
+    }
+
+    /// Iterate over the rows of a table. The table cursor is shared by
+    /// all rows, so every access through an RfcRow first moves the cursor to
+    /// that row. The table is borrowed mutably while iterating so that no
+    /// other code moves the cursor in between.
+    pub fn rows(&mut self) -> Result<RfcRows<'_, 'conn, 'strct>, RfcErrorInfo> {
+        let count = self.row_count()?;
+        Ok(RfcRows {
+            table: self,
+            next: 0,
+            count,
+        })
+    }
+
+    /// The decoded fields of a structure or table parameter
+    pub(crate) fn struct_def_or_err(&self) -> Result<&RfcDecodedFieldDesc<'conn, 'strct>, RfcErrorInfo> {
+        self.field_type.ensure_struct_or_table()?;
         self.struct_def
             .as_ref()
-            .ok_or(RfcErrorInfo::custom("logic error at 01BC"))
-            .map(|s| s.parameters.len() as u32)
+            .ok_or_else(|| missing_struct_def(self.field_type, &self.name))
     }
 
-    pub fn get_field_index_by_name(&mut self, key: &str) -> Result<u32, RfcErrorInfo> {
+    fn struct_def_mut_or_err(&mut self) -> Result<&mut RfcDecodedFieldDesc<'conn, 'strct>, RfcErrorInfo> {
         self.field_type.ensure_struct_or_table()?;
-        let rpd = self
-            .struct_def
+        let field_type = self.field_type;
+        let name = &self.name;
+        self.struct_def
             .as_mut()
-            .ok_or(RfcErrorInfo::custom("Logic error at 01D4"))?;
-        let mut i = 0;
-        for field in &rpd.fields {
-            if field.name.as_str().eq(key) {
-                return Ok(i);
-            }
-            i += 1;
+            .ok_or_else(|| missing_struct_def(field_type, name))
+    }
+
+    /// The decoded fields of a structure parameter. The `parameters` of the
+    /// result can be read with get_value and friends without moving a table
+    /// cursor, e.g. to read a BAPIRETURN structure.
+    pub fn as_struct(&self) -> Result<&RfcDecodedFieldDesc<'conn, 'strct>, RfcErrorInfo> {
+        if self.field_type != RfcType::Structure {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Parameter {} is of type {:?}, not a structure",
+                self.name, self.field_type
+            )));
+        }
+        self.struct_def_or_err()
+    }
+
+    /// The decoded fields of the line type of a table parameter, e.g. to
+    /// see which fields a row has before any row was added. None if the
+    /// parameter is not a table.
+    pub fn line_type(&self) -> Option<&RfcDecodedFieldDesc<'conn, 'strct>> {
+        if self.field_type.is_table() {
+            self.struct_def.as_ref()
+        } else {
+            None
         }
-        Err(RfcErrorInfo::custom("Unknown field"))
+    }
+
+    /// Return the number of fields of a structure, or of the rows of a
+    /// table.
+    pub fn get_field_count(&self) -> Result<u32, RfcErrorInfo> {
+        self.struct_def_or_err().map(|s| s.parameters.len() as u32)
+    }
+
+    /// Return the index of a field of a structure or table. This is a case
+    /// insensitive operation, like get_field.
+    pub fn get_field_index_by_name(&mut self, key: &str) -> Result<u32, RfcErrorInfo> {
+        let rpd = self.struct_def_or_err()?;
+        field_position(&rpd.fields, key)
+            .ok_or_else(|| RfcErrorInfo::custom(&format!("unknown field {} in parameter {}", key, self.name)))
     }
 
     pub fn get_field_by_index(
         &mut self,
         index: u32,
     ) -> Result<&mut RfcParameter<'conn, 'strct>, RfcErrorInfo> {
-        self.field_type.ensure_struct_or_table()?;
-        let rpd = self
-            .struct_def
-            .as_mut()
-            .ok_or(RfcErrorInfo::custom("Logic error at 01D4"))?;
+        let count = self.struct_def_or_err()?.parameters.len();
+        if index as usize >= count {
+            return Err(RfcErrorInfo::custom(&format!(
+                "illegal field index {} for parameter {} with {} fields",
+                index, self.name, count
+            )));
+        }
+        Ok(&mut self.struct_def_mut_or_err()?.parameters[index as usize])
+    }
+
+    /// Get a reference to a field of a structure or table using the field
+    /// name. This is a case insensitive operation.
+    pub fn get_field(&self, name: &str) -> Result<&RfcParameter<'conn, 'strct>, RfcErrorInfo> {
+        let rpd = self.struct_def_or_err()?;
         rpd.parameters
-            .get_mut(index as usize)
-            .ok_or(RfcErrorInfo::custom("illegal index"))
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| RfcErrorInfo::custom(&format!("unknown field {}", name)))
     }
 
-    pub fn set_string(&mut self, value: &str) -> Result<(), RfcErrorInfo> {
-        if !self.direction.can_write() {
-            return Err(RfcErrorInfo::custom("Read-only parameter"));
+    /// Get a mutable reference to a field of a structure or table using the
+    /// field name. This is a case insensitive operation.
+    pub fn get_field_mut(
+        &mut self,
+        name: &str,
+    ) -> Result<&mut RfcParameter<'conn, 'strct>, RfcErrorInfo> {
+        let rpd = self.struct_def_mut_or_err()?;
+        rpd.parameters
+            .iter_mut()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| RfcErrorInfo::custom(&format!("unknown field {}", name)))
+    }
+
+    /// Mark a parameter of a function as active or inactive. Inactive
+    /// parameters are not sent to the SAP system, so the function module
+    /// uses its default value, which for CHANGING parameters differs from
+    /// sending an initial value. Only applies to parameters of a function,
+    /// not to fields of structures.
+    pub fn set_active(&mut self, active: bool) -> Result<(), RfcErrorInfo> {
+        let name = U16CString::from_str(&self.name).map_err(|e| interior_nul(&self.name, e))?;
+        let name = name.into_vec_with_nul();
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcSetParameterActive(self.fun, name.as_ptr(), i32::from(active), &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
         }
-        if &self.field_type == &RfcType::String || &self.field_type == &RfcType::Char {
-            let v = U16CString::from_str(value);
-            if let Err(e) = v {
-                return Err(RfcErrorInfo::custom(&e.to_string()));
+        Ok(())
+    }
+
+    /// Reset the parameter to the initial value of its type: blanks, zero,
+    /// no bytes, the initial date or time. All fields of a structure are
+    /// cleared, all rows of a table are deleted. Afterwards the parameter
+    /// counts as not written, see was_written.
+    pub fn clear(&mut self) -> Result<(), RfcErrorInfo> {
+        match self.field_type {
+            RfcType::Char | RfcType::String => self.set_string("")?,
+            RfcType::Num => self.set_num("")?,
+            RfcType::Date => self.set_string_converted("00000000")?,
+            RfcType::Time => self.set_string_converted("000000")?,
+            RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => self.set_string_converted("0")?,
+            RfcType::Int | RfcType::Int1 | RfcType::Int2 => self.set_int(0)?,
+            RfcType::Int8 => self.set_int8(0)?,
+            RfcType::Float => self.set_float(0.0)?,
+            RfcType::Byte => self.set_bytes(&[])?,
+            RfcType::XString => self.set_xstring(&[])?,
+            RfcType::Structure => {
+                if let Some(sd) = self.struct_def.as_mut() {
+                    for field in sd.parameters.iter_mut() {
+                        field.clear()?;
+                    }
+                }
             }
-            let v = v.unwrap();
-            let mut err_trunk = RfcErrorInfo::new();
-            let v = v.into_vec_with_nul();
-            let res = unsafe {
+            RfcType::Table => self.delete_all_rows()?,
+            t => {
+                return Err(RfcErrorInfo::custom(&format!(
+                    "clear does not support parameter {} of type {:?}",
+                    self.name, t
+                )))
+            }
+        }
+        self.written = false;
+        Ok(())
+    }
+
+    /// Set the parameter from an RfcValue, using the typed setter that
+    /// matches the variant. Fails if the variant does not fit the type of
+    /// the parameter.
+    pub fn set_value(&mut self, value: RfcValue) -> Result<(), RfcErrorInfo> {
+        match (value, self.field_type) {
+            (RfcValue::RfcString(v), RfcType::Char | RfcType::String) => self.set_string(&v),
+            (
+                RfcValue::RfcString(v),
+                RfcType::Num | RfcType::Date | RfcType::Time | RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34,
+            ) => self.set_string_converted(&v),
+            (RfcValue::Chars(v), RfcType::Char) => self.set_string(&v),
+            (RfcValue::Chars(v), RfcType::Num) => self.set_num(&v),
+            (RfcValue::Chars(v), RfcType::Date | RfcType::Time) => self.set_string_converted(&v),
+            (RfcValue::Int(v), RfcType::Int | RfcType::Int1 | RfcType::Int2) => self.set_int(v),
+            (RfcValue::Int(v), RfcType::Int8) => self.set_int8(v),
+            (RfcValue::Float(v), RfcType::Float) => self.set_float(v),
+            (RfcValue::Bytes(v), RfcType::Byte) => self.set_bytes(&v),
+            (RfcValue::Bytes(v), RfcType::XString) => self.set_xstring(&v),
+            #[cfg(feature = "chrono")]
+            (RfcValue::Date(v), RfcType::Date) => self.set_date(v),
+            #[cfg(feature = "chrono")]
+            (RfcValue::Time(v), RfcType::Time) => self.set_time(v),
+            (v, t) => Err(RfcErrorInfo::custom(&format!(
+                "Value {:?} does not match parameter {} of type {:?}",
+                v, self.name, t
+            ))),
+        }
+    }
+
+    /// Read the parameter into the RfcValue variant matching its type.
+    /// Packed numbers and decimal floats are returned as RfcString. With the
+    /// `chrono` feature dates and times are returned as Date and Time, except
+    /// for an initial date, which is returned as Chars with the raw value.
+    /// Structures and tables have to be read through get_field and rows.
+    pub fn get_value(&self) -> Result<RfcValue, RfcErrorInfo> {
+        match self.field_type {
+            RfcType::Char => Ok(RfcValue::Chars(self.get_chars()?)),
+            RfcType::Num => Ok(RfcValue::Chars(self.get_num()?)),
+            RfcType::String | RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => {
+                Ok(RfcValue::RfcString(self.get_string()?))
+            }
+            RfcType::Int | RfcType::Int1 | RfcType::Int2 => Ok(RfcValue::Int(self.get_int()?)),
+            RfcType::Int8 => Ok(RfcValue::Int(self.get_int8()?)),
+            RfcType::Float => Ok(RfcValue::Float(self.get_float()?)),
+            RfcType::Byte => Ok(RfcValue::Bytes(self.get_bytes()?)),
+            RfcType::XString => Ok(RfcValue::Bytes(self.get_xstring()?)),
+            #[cfg(feature = "chrono")]
+            RfcType::Date => match self.get_date_opt()? {
+                Some(date) => Ok(RfcValue::Date(date)),
+                None => Ok(RfcValue::Chars(self.get_chars()?)),
+            },
+            #[cfg(feature = "chrono")]
+            RfcType::Time => Ok(RfcValue::Time(self.get_time()?)),
+            #[cfg(not(feature = "chrono"))]
+            RfcType::Date | RfcType::Time => Ok(RfcValue::Chars(self.get_chars()?)),
+            RfcType::Structure | RfcType::Table => Err(RfcErrorInfo::custom(&format!(
+                "Parameter {} is a structure or table; use get_field or rows to read it",
+                self.name
+            ))),
+            t => Err(RfcErrorInfo::custom(&format!(
+                "get_value does not support parameter {} of type {:?}",
+                self.name, t
+            ))),
+        }
+    }
+
+    pub fn set_string(&mut self, value: &str) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if &self.field_type == &RfcType::String || &self.field_type == &RfcType::Char {
+            let (v, len) = encode_value(value, &self.name)?;
+            self.check_char_width(len as usize)?;
+            let mut err_trunk = RfcErrorInfo::new();
+            let res = unsafe {
                 self.rfc_api.RfcSetCharsByIndex(
                     self.fun,
                     self.index,
                     v.as_ptr(),
-                    value.len() as u32,
+                    len,
                     &mut err_trunk,
                 )
             };
             if !res.is_ok() {
                 return Err(err_trunk);
             }
+            self.written = true;
             Ok(())
         } else {
             Err(RfcErrorInfo::custom(
@@ -601,22 +1425,435 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
         }
     }
 
+    fn check_char_width(&self, len: usize) -> Result<(), RfcErrorInfo> {
+        check_char_width(self.field_type, self.len, len, &self.name)
+    }
+
+    /// Set a CHAR or STRING value from UTF-16 code units, which are passed
+    /// to the RFC library as they are. Unlike set_string this accepts NUL
+    /// characters and unpaired surrogates.
+    pub fn set_chars_u16(&mut self, value: &[u16]) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::String && self.field_type != RfcType::Char {
+            return Err(RfcErrorInfo::custom(
+                "Not a string datatype, cannot use set_chars_u16",
+            ));
+        }
+        self.check_char_width(value.len())?;
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcSetCharsByIndex(self.fun, self.index, value.as_ptr(), value.len() as u32, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
     pub fn set_int(&mut self, value: i64) -> Result<(), RfcErrorInfo> {
         if !self.direction.can_write() {
             return Err(RfcErrorInfo::custom("Read-only parameter"));
         }
+        if self.field_type == RfcType::Int8 {
+            return Err(RfcErrorInfo::custom(
+                "Parameter is of type INT8; use set_int8 instead of set_int",
+            ));
+        }
+        // The RFC library would silently truncate values that do not fit
+        let (min, max) = int_range(self.field_type);
+        if value < min || value > max {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Value {} out of range for {} field {}, which holds {} to {}",
+                value, self.field_type, self.name, min, max
+            )));
+        }
         let mut err_trunk = RfcErrorInfo::new();
-        let res = unsafe { self.rfc_api.RfcSetIntByIndex(self.fun, self.index, value, &mut err_trunk) };
+        let res = unsafe { self.rfc_api.RfcSetIntByIndex(self.fun, self.index, value as i32, &mut err_trunk) };
         if !res.is_ok() {
             return Err(err_trunk);
         }
+        self.written = true;
         Ok(())
     }
 
-    pub fn get_chars(&self) -> Result<String, RfcErrorInfo> {
-        if !self.direction.can_read() {
+    pub fn get_int(&self) -> Result<i64, RfcErrorInfo> {
+        self.ensure_readable()?;
+        match self.field_type {
+            RfcType::Int | RfcType::Int1 | RfcType::Int2 => {}
+            RfcType::Int8 => {
+                return Err(RfcErrorInfo::custom(
+                    "Parameter is of type INT8; use get_int8 instead of get_int",
+                ))
+            }
+            _ => {
+                return Err(RfcErrorInfo::custom(
+                    "Not an integer datatype, cannot use get_int",
+                ))
+            }
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let mut value: i32 = 0;
+        let res = unsafe { self.rfc_api.RfcGetIntByIndex(self.fun, self.index, &mut value, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(i64::from(value))
+    }
+
+    /// Get an INT1 field as u8
+    pub fn get_int1(&self) -> Result<u8, RfcErrorInfo> {
+        if self.field_type != RfcType::Int1 {
+            return Err(RfcErrorInfo::custom(
+                "Not of type INT1; cannot use get_int1",
+            ));
+        }
+        let value = self.get_int()?;
+        u8::try_from(value).map_err(|_| self.int_out_of_range(value))
+    }
+
+    /// Get an INT2 field as i16
+    pub fn get_int2(&self) -> Result<i16, RfcErrorInfo> {
+        if self.field_type != RfcType::Int2 {
+            return Err(RfcErrorInfo::custom(
+                "Not of type INT2; cannot use get_int2",
+            ));
+        }
+        let value = self.get_int()?;
+        i16::try_from(value).map_err(|_| self.int_out_of_range(value))
+    }
+
+    fn int_out_of_range(&self, value: i64) -> RfcErrorInfo {
+        RfcErrorInfo::custom(&format!(
+            "Value {} of field {} is out of range for {}",
+            value, self.name, self.field_type
+        ))
+    }
+
+    pub fn set_int8(&mut self, value: i64) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
             return Err(RfcErrorInfo::custom("Read-only parameter"));
         }
+        if self.field_type != RfcType::Int8 {
+            return Err(RfcErrorInfo::custom(
+                "Not of type INT8; cannot use set_int8",
+            ));
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_api.RfcSetInt8ByIndex(self.fun, self.index, value, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
+    pub fn get_int8(&self) -> Result<i64, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::Int8 {
+            return Err(RfcErrorInfo::custom(
+                "Not of type INT8; cannot use get_int8",
+            ));
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let mut value: i64 = 0;
+        let res = unsafe { self.rfc_api.RfcGetInt8ByIndex(self.fun, self.index, &mut value, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(value)
+    }
+
+    pub fn set_float(&mut self, value: f64) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::Float {
+            return Err(RfcErrorInfo::custom(
+                "Not of type FLOAT; cannot use set_float",
+            ));
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_api.RfcSetFloatByIndex(self.fun, self.index, value, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
+    pub fn get_float(&self) -> Result<f64, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::Float {
+            return Err(RfcErrorInfo::custom(
+                "Not of type FLOAT; cannot use get_float",
+            ));
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let mut value: f64 = 0.0;
+        let res = unsafe { self.rfc_api.RfcGetFloatByIndex(self.fun, self.index, &mut value, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(value)
+    }
+
+    /// Set a DATE parameter. Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn set_date(&mut self, date: NaiveDate) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::Date {
+            return Err(RfcErrorInfo::custom(
+                "Not of type DATE; cannot use set_date",
+            ));
+        }
+        if date.year() < 0 || date.year() > 9999 {
+            return Err(RfcErrorInfo::custom(
+                "Year out of range; DATE fields hold years 0000 to 9999",
+            ));
+        }
+        let v = format!("{:04}{:02}{:02}", date.year(), date.month(), date.day());
+        let v: Vec<u16> = v.encode_utf16().collect();
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_api.RfcSetDateByIndex(self.fun, self.index, v.as_ptr(), &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
+    /// Get a DATE parameter. Fails if the date is initial (00000000 or
+    /// blank); use get_date_opt if that is a legitimate value for you.
+    /// Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn get_date(&self) -> Result<NaiveDate, RfcErrorInfo> {
+        self.get_date_opt()?
+            .ok_or_else(|| RfcErrorInfo::custom("Initial date; use get_date_opt to read it"))
+    }
+
+    /// Get a DATE parameter, mapping the initial date (00000000 or blank)
+    /// to None. Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn get_date_opt(&self) -> Result<Option<NaiveDate>, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::Date {
+            return Err(RfcErrorInfo::custom(
+                "Not of type DATE; cannot use get_date",
+            ));
+        }
+        let mut buf = [0u16; 8];
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_api.RfcGetDateByIndex(self.fun, self.index, buf.as_mut_ptr(), &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        if is_initial_chars(&buf) {
+            return Ok(None);
+        }
+        let v = String::from_utf16_lossy(&buf);
+        let date = parse_digits(&v, &[4, 2, 2]).and_then(|ymd| {
+            NaiveDate::from_ymd_opt(ymd[0] as i32, ymd[1], ymd[2])
+        });
+        match date {
+            Some(date) => Ok(Some(date)),
+            None => Err(RfcErrorInfo::custom(&format!("Invalid date: {}", v))),
+        }
+    }
+
+    /// Set a TIME parameter. Fractions of a second are dropped.
+    /// Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn set_time(&mut self, time: NaiveTime) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::Time {
+            return Err(RfcErrorInfo::custom(
+                "Not of type TIME; cannot use set_time",
+            ));
+        }
+        // A leap second is reported as second 60 by chrono, which does not
+        // fit into an ABAP TIME field.
+        let v = format!("{:02}{:02}{:02}", time.hour(), time.minute(), min(time.second(), 59));
+        let v: Vec<u16> = v.encode_utf16().collect();
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_api.RfcSetTimeByIndex(self.fun, self.index, v.as_ptr(), &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
+    /// Get a TIME parameter. The initial time (000000 or blank) is
+    /// returned as midnight. Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn get_time(&self) -> Result<NaiveTime, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::Time {
+            return Err(RfcErrorInfo::custom(
+                "Not of type TIME; cannot use get_time",
+            ));
+        }
+        let mut buf = [0u16; 6];
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_api.RfcGetTimeByIndex(self.fun, self.index, buf.as_mut_ptr(), &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        if is_initial_chars(&buf) {
+            return Ok(NaiveTime::MIN);
+        }
+        let v = String::from_utf16_lossy(&buf);
+        let time = parse_digits(&v, &[2, 2, 2]).and_then(|hms| {
+            NaiveTime::from_hms_opt(hms[0], hms[1], hms[2])
+        });
+        time.ok_or_else(|| RfcErrorInfo::custom(&format!("Invalid time: {}", v)))
+    }
+
+    /// Set a UTCLONG timestamp. The value is passed to the RFC library as
+    /// YYYYMMDDHHMMSSmmmuuun, i.e. with a precision of 100 nanoseconds.
+    /// Years outside 0001 to 9999 are rejected with RfcInvalidParameter.
+    #[cfg(feature = "chrono")]
+    pub fn set_utc_long(&mut self, ts: NaiveDateTime) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::UtcLong {
+            return Err(RfcErrorInfo::custom(
+                "Not of type UTCLONG; cannot use set_utc_long",
+            ));
+        }
+        check_utc_long_year(ts.year())?;
+        // chrono reports a leap second as more than 10^9 nanoseconds
+        let fraction = min(ts.nanosecond() / 100, 9_999_999);
+        let v = format!(
+            "{:04}{:02}{:02}{:02}{:02}{:02}{:07}",
+            ts.year(),
+            ts.month(),
+            ts.day(),
+            ts.hour(),
+            ts.minute(),
+            ts.second(),
+            fraction
+        );
+        let v: Vec<u16> = v.encode_utf16().collect();
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcSetCharsByIndex(self.fun, self.index, v.as_ptr(), v.len() as u32, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
+    /// Read a UTCLONG timestamp. Fails for an initial timestamp; use
+    /// get_utc_long_opt if the field may be empty.
+    #[cfg(feature = "chrono")]
+    pub fn get_utc_long(&self) -> Result<NaiveDateTime, RfcErrorInfo> {
+        self.get_utc_long_opt()?
+            .ok_or_else(|| RfcErrorInfo::custom("Initial timestamp; use get_utc_long_opt to read it"))
+    }
+
+    /// Read a UTCLONG timestamp, returning None for an initial timestamp
+    #[cfg(feature = "chrono")]
+    pub fn get_utc_long_opt(&self) -> Result<Option<NaiveDateTime>, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::UtcLong {
+            return Err(RfcErrorInfo::custom(
+                "Not of type UTCLONG; cannot use get_utc_long",
+            ));
+        }
+        let mut buf = [0u16; 21];
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcGetCharsByIndex(self.fun, self.index, buf.as_mut_ptr(), buf.len() as u32, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        if is_initial_chars(&buf) {
+            return Ok(None);
+        }
+        let v = String::from_utf16_lossy(&buf);
+        let ts = parse_digits(&v, &[4, 2, 2, 2, 2, 2, 7]).and_then(|p| {
+            NaiveDate::from_ymd_opt(p[0] as i32, p[1], p[2])?.and_hms_nano_opt(p[3], p[4], p[5], p[6] * 100)
+        });
+        match ts {
+            Some(ts) => Ok(Some(ts)),
+            None => Err(RfcErrorInfo::custom(&format!("Invalid timestamp: {}", v))),
+        }
+    }
+
+    /// Set a NUM parameter. Only ASCII digits are accepted; the value is
+    /// left-padded with zeros to the width of the field.
+    pub fn set_num(&mut self, value: &str) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::Num {
+            return Err(RfcErrorInfo::custom(
+                "Not of type NUM; cannot use set_num",
+            ));
+        }
+        if !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Value {} of NUM parameter {} contains non-digits",
+                value, self.name
+            )));
+        }
+        let width = self.char_width() as usize;
+        if value.len() > width {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Value of {} digits exceeds the width of NUM parameter {} ({} digits)",
+                value.len(),
+                self.name,
+                width
+            )));
+        }
+        let v = format!("{:0>width$}", value, width = width);
+        let v: Vec<u16> = v.encode_utf16().collect();
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcSetNumByIndex(self.fun, self.index, v.as_ptr(), v.len() as u32, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.written = true;
+        Ok(())
+    }
+
+    /// Get a NUM parameter. The value is returned as is, including
+    /// leading zeros.
+    pub fn get_num(&self) -> Result<String, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::Num {
+            return Err(RfcErrorInfo::custom(
+                "Not of type NUM; cannot use get_num",
+            ));
+        }
+        let width = self.char_width();
+        let mut buf = vec![0u16; width as usize];
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcGetNumByIndex(self.fun, self.index, buf.as_mut_ptr(), width, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        decode_name(&buf, &self.name)
+    }
+
+    pub fn get_chars(&self) -> Result<String, RfcErrorInfo> {
+        self.ensure_readable()?;
 
         /*
         if &self.field_type != &RfcType::String && &self.field_type != &RfcType::XString {
@@ -627,10 +1864,11 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
         */
 
         let mut err_trunk = RfcErrorInfo::new();
-        let mut buf = Vec::new();
-        let mut reserve_len = self.len;
-        reserve_len += 1;
-        buf.reserve_exact(reserve_len as usize * 2);
+        // The SDK does not NUL terminate the value, so start from a zeroed
+        // buffer and drop the NULs after the value. NULs within the value
+        // are kept. The buffer is sized in characters, like in get_num.
+        let reserve_len = self.char_width() + 1;
+        let mut buf = vec![0u16; reserve_len as usize];
         {
             let res = unsafe {
                 self.rfc_api.RfcGetCharsByIndex(
@@ -645,22 +1883,62 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
                 return Err(err_trunk);
             }
         }
-        let s = unsafe { U16CString::from_ptr(buf.as_ptr() as *const u16, reserve_len as usize) };
-        if let Err(e) = s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
+        let end = value_end(&buf);
+        String::from_utf16(&buf[..end]).map_err(|e| invalid_utf16(&self.name, e))
+    }
+
+    /// Like get_chars, but reads into `buf`, which is cleared first. Reusing
+    /// the same String for many rows avoids an allocation per field; fields
+    /// of up to 511 characters are also read without a temporary buffer.
+    pub fn read_chars_into(&self, buf: &mut String) -> Result<(), RfcErrorInfo> {
+        self.ensure_readable()?;
+        let reserve_len = self.char_width() + 1;
+        let mut stack_buf = [0u16; 512];
+        let mut heap_buf = Vec::new();
+        let units: &mut [u16] = if reserve_len as usize <= stack_buf.len() {
+            &mut stack_buf[..reserve_len as usize]
+        } else {
+            heap_buf.resize(reserve_len as usize, 0);
+            &mut heap_buf
+        };
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcGetCharsByIndex(self.fun, self.index, units.as_mut_ptr(), reserve_len, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
         }
-        let s = s.unwrap().to_string();
-        if let Err(e) = s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
+        let end = value_end(units);
+        buf.clear();
+        for c in char::decode_utf16(units[..end].iter().copied()) {
+            buf.push(c.map_err(|e| invalid_utf16(&self.name, e))?);
         }
-        let s = s.unwrap();
-        Ok(s)
+        Ok(())
+    }
+
+    /// Like get_chars, but without the trailing spaces and NULs that pad
+    /// fixed length fields
+    pub fn get_chars_trimmed(&self) -> Result<String, RfcErrorInfo> {
+        let s = self.get_chars()?;
+        Ok(s.trim_end_matches([' ', '\0']).to_string())
     }
 
     pub fn get_string(&self) -> Result<String, RfcErrorInfo> {
-        if !self.direction.can_read() {
-            return Err(RfcErrorInfo::custom("Read-only parameter"));
-        }
+        let buf = self.get_string_u16()?;
+        String::from_utf16(&buf).map_err(|e| invalid_utf16(&self.name, e))
+    }
+
+    /// Like get_string, but returns the UTF-16 code units of the value as
+    /// little endian bytes without checking that they are valid UTF-16,
+    /// e.g. for systems that put binary data into STRING fields.
+    pub fn get_raw_string(&self) -> Result<Vec<u8>, RfcErrorInfo> {
+        let buf = self.get_string_u16()?;
+        Ok(buf.iter().flat_map(|c| c.to_le_bytes()).collect())
+    }
+
+    /// Read the value of the field as UTF-16 code units
+    fn get_string_u16(&self) -> Result<Vec<u16>, RfcErrorInfo> {
+        self.ensure_readable()?;
 
         /*
         if &self.field_type != &RfcType::String && &self.field_type != &RfcType::XString {
@@ -671,7 +1949,6 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
         */
 
         let mut err_trunk = RfcErrorInfo::new();
-        let mut buf = Vec::new();
         let mut reserve_len = 0;
         {
             let res = unsafe {
@@ -681,10 +1958,8 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
                 return Err(err_trunk);
             }
         }
-        // This is an ungenau wissenschaft; utterly undocumented how to properly
-        // use this API.
-        reserve_len += 1;
-        buf.reserve_exact(reserve_len as usize * 2);
+        let mut buf = string_buffer(reserve_len);
+        let reserve_len = buf.len() as u32;
         let mut len = 0;
         {
             let res = unsafe {
@@ -701,16 +1976,74 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
                 return Err(err_trunk);
             }
         }
-        let s = unsafe { U16CString::from_ptr(buf.as_ptr() as *const u16, len as usize) };
-        if let Err(e) = s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
+        Ok(take_string(buf, len))
+    }
+
+    /// Set any parameter from its string representation, letting the RFC
+    /// library do the conversion.
+    pub(crate) fn set_string_converted(&mut self, value: &str) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
         }
-        let s = s.unwrap().to_string();
-        if let Err(e) = s {
-            return Err(RfcErrorInfo::custom(&e.to_string()));
+        let (v, len) = encode_value(value, &self.name)?;
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcSetStringByIndex(self.fun, self.index, v.as_ptr(), len, &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
         }
-        let s = s.unwrap();
-        Ok(s)
+        self.written = true;
+        Ok(())
+    }
+
+    /// Set a packed decimal (BCD) or decimal floating point (DECFLOAT16,
+    /// DECFLOAT34) parameter. For BCD fields, values with more decimal
+    /// places than the field provides are rejected rather than rounded.
+    /// Requires the `decimal` feature.
+    #[cfg(feature = "decimal")]
+    pub fn set_decimal(&mut self, value: Decimal) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        match self.field_type {
+            RfcType::Bcd => {
+                if value.scale() > self.decimals {
+                    return Err(RfcErrorInfo::custom(&format!(
+                        "Value {} has {} decimal places but parameter {} only has {}",
+                        value,
+                        value.scale(),
+                        self.name,
+                        self.decimals
+                    )));
+                }
+            }
+            RfcType::Decf16 | RfcType::Decf34 => {}
+            _ => {
+                return Err(RfcErrorInfo::custom(
+                    "Not a decimal datatype, cannot use set_decimal",
+                ))
+            }
+        }
+        self.set_string_converted(&value.to_string())
+    }
+
+    /// Get a packed decimal (BCD) or decimal floating point (DECFLOAT16,
+    /// DECFLOAT34) parameter. Requires the `decimal` feature.
+    #[cfg(feature = "decimal")]
+    pub fn get_decimal(&self) -> Result<Decimal, RfcErrorInfo> {
+        match self.field_type {
+            RfcType::Bcd | RfcType::Decf16 | RfcType::Decf34 => {}
+            _ => {
+                return Err(RfcErrorInfo::custom(
+                    "Not a decimal datatype, cannot use get_decimal",
+                ))
+            }
+        }
+        let v = self.get_string()?;
+        parse_abap_decimal(&v).map_err(|e| {
+            RfcErrorInfo::custom(&format!("Invalid decimal value {}: {}", v.trim(), e))
+        })
     }
 
     pub fn set_xstring(&mut self, v: &[u8]) -> Result<(), RfcErrorInfo> {
@@ -718,9 +2051,7 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
             return Err(RfcErrorInfo::custom("Read-only parameter"));
         }
         if &self.field_type != &RfcType::XString {
-            return Err(RfcErrorInfo::custom(
-                "Not of type XSTRING; cannot use get_string",
-            ));
+            return Err(not_xstring(self.field_type, "set_xstring", "set_bytes"));
         }
 
         let mut err_trunk = RfcErrorInfo::new();
@@ -734,6 +2065,7 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
             )
         };
         if res.is_ok() {
+            self.written = true;
             Ok(())
         } else {
             Err(err_trunk)
@@ -741,13 +2073,9 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
     }
 
     pub fn get_xstring(&self) -> Result<Vec<u8>, RfcErrorInfo> {
-        if !self.direction.can_read() {
-            return Err(RfcErrorInfo::custom("Read-only parameter"));
-        }
+        self.ensure_readable()?;
         if &self.field_type != &RfcType::XString {
-            return Err(RfcErrorInfo::custom(
-                "Not of type XSTRING; cannot use get_string",
-            ));
+            return Err(not_xstring(self.field_type, "get_xstring", "get_bytes"));
         }
         let mut err_trunk = RfcErrorInfo::new();
         let mut reserve_len = 0;
@@ -759,22 +2087,97 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
                 return Err(err_trunk);
             }
         }
+        // For XSTRING fields RfcGetStringLength reports the length in
+        // bytes. Should the value still not fit, the SDK reports the
+        // required length and the read is retried once with that size.
         let mut out_len = 0;
-        let mut out_buf = Vec::new();
-        out_buf.reserve_exact(reserve_len as usize);
-        unsafe {
-            out_buf.set_len(reserve_len as usize);
-        }
-        let res = unsafe {
+        let mut out_buf = vec![0u8; reserve_len as usize];
+        let mut res = unsafe {
             self.rfc_api.RfcGetXStringByIndex(
                 self.fun,
                 self.index,
                 out_buf.as_mut_ptr(),
-                reserve_len,
+                out_buf.len() as u32,
                 &mut out_len,
                 &mut err_trunk,
             )
         };
+        if res == RfcRc::RfcBufferTooSmall && out_len as usize > out_buf.len() {
+            out_buf = vec![0u8; out_len as usize];
+            err_trunk = RfcErrorInfo::new();
+            res = unsafe {
+                self.rfc_api.RfcGetXStringByIndex(
+                    self.fun,
+                    self.index,
+                    out_buf.as_mut_ptr(),
+                    out_buf.len() as u32,
+                    &mut out_len,
+                    &mut err_trunk,
+                )
+            };
+        }
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        out_buf.truncate(out_len as usize);
+        Ok(out_buf)
+    }
+
+    /// Set a fixed length BYTE (RAW) parameter. Values shorter than the
+    /// field are padded with zero bytes by the RFC library.
+    pub fn set_bytes(&mut self, v: &[u8]) -> Result<(), RfcErrorInfo> {
+        if !self.direction.can_write() {
+            return Err(RfcErrorInfo::custom("Read-only parameter"));
+        }
+        if self.field_type != RfcType::Byte {
+            return Err(RfcErrorInfo::custom(
+                "Not of type BYTE; cannot use set_bytes",
+            ));
+        }
+        if v.len() > self.len as usize {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Value of {} bytes exceeds the field length of {} bytes",
+                v.len(),
+                self.len
+            )));
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_api.RfcSetBytesByIndex(
+                self.fun,
+                self.index,
+                v.as_ptr(),
+                v.len() as u32,
+                &mut err_trunk,
+            )
+        };
+        if res.is_ok() {
+            self.written = true;
+            Ok(())
+        } else {
+            Err(err_trunk)
+        }
+    }
+
+    /// Get a fixed length BYTE (RAW) parameter
+    pub fn get_bytes(&self) -> Result<Vec<u8>, RfcErrorInfo> {
+        self.ensure_readable()?;
+        if self.field_type != RfcType::Byte {
+            return Err(RfcErrorInfo::custom(
+                "Not of type BYTE; cannot use get_bytes",
+            ));
+        }
+        let mut err_trunk = RfcErrorInfo::new();
+        let mut out_buf = vec![0u8; self.len as usize];
+        let res = unsafe {
+            self.rfc_api.RfcGetBytesByIndex(
+                self.fun,
+                self.index,
+                out_buf.as_mut_ptr(),
+                self.len,
+                &mut err_trunk,
+            )
+        };
         if res.is_ok() {
             Ok(out_buf)
         } else {
@@ -783,40 +2186,259 @@ impl<'conn, 'strct: 'conn> RfcParameter<'conn, 'strct> {
     }
 }
 
-#[allow(non_snake_case)]
-#[derive(WrapperApi)]
-pub struct RfcApi {
+fn missing_struct_def(field_type: RfcType, name: &str) -> RfcErrorInfo {
+    RfcErrorInfo::custom(&format!(
+        "field_type is {:?} but the structure definition was not decoded for parameter {}",
+        field_type, name
+    ))
+}
+
+/// Encode a value for the RFC library. Returns the NUL terminated UTF-16
+/// code units and the length without the NUL, which is what the SDK expects
+/// as length; neither the UTF-8 byte count nor the number of chars.
+fn encode_value(value: &str, name: &str) -> Result<(Vec<u16>, u32), RfcErrorInfo> {
+    let v = U16CString::from_str(value).map_err(|e| interior_nul(&format!("Value for {}", name), e))?;
+    let len = v.len() as u32;
+    Ok((v.into_vec_with_nul(), len))
+}
+
+/// Buffer for RfcGetStringByIndex. The length is reported in UTF-16 code
+/// units without the terminating NUL, while the buffer length passed to
+/// RfcGetStringByIndex has to include it.
+fn string_buffer(reported_len: u32) -> Vec<u16> {
+    vec![0u16; reported_len as usize + 1]
+}
+
+/// The value in a buffer of string_buffer, given the length the RFC
+/// library returned
+fn take_string(mut buf: Vec<u16>, len: u32) -> Vec<u16> {
+    buf.truncate(min(len as usize, buf.len()));
+    buf
+}
+
+/// Check that a value of `len` UTF-16 code units fits into a CHAR field of
+/// `field_len` bytes, which holds field_len / 2 characters in the unicode
+/// layout. Other types, e.g. STRING, have no limit.
+fn check_char_width(field_type: RfcType, field_len: u32, len: usize, name: &str) -> Result<(), RfcErrorInfo> {
+    let width = field_len as usize / 2;
+    if field_type == RfcType::Char && len > width {
+        return Err(RfcErrorInfo::custom(&format!(
+            "Value of {} chars exceeds the field width of {} chars of {}",
+            len, width, name
+        )));
+    }
+    Ok(())
+}
+
+/// Position of the field named `key`, ignoring case
+fn field_position(fields: &[RfcDecodedField], key: &str) -> Option<u32> {
+    fields.iter().position(|f| f.name.eq_ignore_ascii_case(key)).map(|i| i as u32)
+}
+
+/// Parse a decimal in the format of the RFC library, which puts the sign
+/// behind negative BCD numbers, e.g. "1234.56-"
+#[cfg(feature = "decimal")]
+fn parse_abap_decimal(v: &str) -> Result<Decimal, rust_decimal::Error> {
+    let v = v.trim();
+    match v.strip_suffix('-') {
+        Some(abs) => Decimal::from_str(abs.trim()).map(|d| -d),
+        None => Decimal::from_str(v).or_else(|_| Decimal::from_scientific(v)),
+    }
+}
+
+/// Range of values of an INT1, INT2 or INT field
+fn int_range(field_type: RfcType) -> (i64, i64) {
+    match field_type {
+        RfcType::Int1 => (0, i64::from(u8::MAX)),
+        RfcType::Int2 => (i64::from(i16::MIN), i64::from(i16::MAX)),
+        _ => (i64::from(i32::MIN), i64::from(i32::MAX)),
+    }
+}
+
+/// Error for an XSTRING accessor used on another type, pointing to the
+/// accessor for fixed length RAW fields where that applies
+fn not_xstring(field_type: RfcType, method: &str, raw_method: &str) -> RfcErrorInfo {
+    if field_type == RfcType::Byte {
+        RfcErrorInfo::custom(&format!(
+            "Not of type XSTRING but RAW; use {} instead of {}",
+            raw_method, method
+        ))
+    } else {
+        RfcErrorInfo::custom(&format!("Not of type XSTRING; cannot use {}", method))
+    }
+}
+
+/// Length of the value in a zero-initialized character buffer: the whole
+/// buffer without the trailing NULs
+fn value_end(buf: &[u16]) -> usize {
+    buf.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1)
+}
+
+/// UTCLONG holds timestamps from 0001-01-01 to 9999-12-31
+#[cfg(feature = "chrono")]
+fn check_utc_long_year(year: i32) -> Result<(), RfcErrorInfo> {
+    if (1..=9999).contains(&year) {
+        return Ok(());
+    }
+    let mut err = RfcErrorInfo::custom("Year out of range; UTCLONG fields hold years 0001 to 9999");
+    err.code = RfcRc::RfcInvalidParameter;
+    Err(err)
+}
+
+/// Return true if a fixed-width character buffer holds an initial value,
+/// i.e. only zeros, blanks or NULs.
+#[cfg(feature = "chrono")]
+fn is_initial_chars(buf: &[u16]) -> bool {
+    buf.iter().all(|&c| c == 0 || c == u16::from(b'0') || c == u16::from(b' '))
+}
+
+/// Split a string of ASCII digits into numbers of the given widths. Returns
+/// None if the string is too short or contains anything but digits.
+#[cfg(feature = "chrono")]
+fn parse_digits(s: &str, widths: &[usize]) -> Option<Vec<u32>> {
+    let mut res = Vec::with_capacity(widths.len());
+    let mut pos = 0;
+    for width in widths {
+        let part = s.get(pos..pos + width)?;
+        if !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        res.push(part.parse().ok()?);
+        pos += width;
+    }
+    Some(res)
+}
+
+#[allow(non_snake_case)]
+#[derive(WrapperApi)]
+pub struct RfcApi {
+    #[allow(non_snake_case)]
+    RfcOpenConnection: unsafe extern "C" fn(
+        parameters: *const RfcConnectionParameter,
+        param_count: u32,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcConnectionHandle,
+
+    #[allow(non_snake_case)]
+    RfcGetVersion: unsafe extern "C" fn(
+        major_version: *mut u32,
+        minor_version: *mut u32,
+        patch_level: *mut u32,
+    ) -> *const u16,
+
+    #[allow(non_snake_case)]
+    RfcGetFunctionDesc: unsafe extern "C" fn(
+        handle: *mut RfcConnectionHandle,
+        func_name: *const u16,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcFunctionDescHandle,
+
+    #[allow(non_snake_case)]
+    RfcCreateFunction: unsafe extern "C" fn(
+        handle: *mut RfcFunctionDescHandle,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcDataContainerHandle,
+
+    #[allow(non_snake_case)]
+    RfcGetCharsByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: *mut u16,
+        length: u32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetCharsByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: *const u16,
+        length: u32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetIntByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: i32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
     #[allow(non_snake_case)]
-    RfcOpenConnection: unsafe extern "C" fn(
-        parameters: *const RfcConnectionParameter,
-        param_count: u32,
+    RfcGetIntByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: *mut i32,
         error: *mut RfcErrorInfo,
-    ) -> *mut RfcConnectionHandle,
+    ) -> RfcRc,
 
     #[allow(non_snake_case)]
-    RfcGetFunctionDesc: unsafe extern "C" fn(
-        handle: *mut RfcConnectionHandle,
-        func_name: *const u16,
+    RfcSetInt8ByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: i64,
         error: *mut RfcErrorInfo,
-    ) -> *mut RfcFunctionDescHandle,
+    ) -> RfcRc,
 
     #[allow(non_snake_case)]
-    RfcCreateFunction: unsafe extern "C" fn(
-        handle: *mut RfcFunctionDescHandle,
+    RfcGetInt8ByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: *mut i64,
         error: *mut RfcErrorInfo,
-    ) -> *mut RfcDataContainerHandle,
+    ) -> RfcRc,
 
     #[allow(non_snake_case)]
-    RfcGetCharsByIndex: unsafe extern "C" fn(
+    RfcSetFloatByIndex: unsafe extern "C" fn(
         handle: *mut RfcDataContainerHandle,
         index: u32,
-        value: *mut u16,
-        length: u32,
+        value: f64,
         error: *mut RfcErrorInfo,
     ) -> RfcRc,
 
     #[allow(non_snake_case)]
-    RfcSetCharsByIndex: unsafe extern "C" fn(
+    RfcGetFloatByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        value: *mut f64,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetDateByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        date: *const u16,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcGetDateByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        date: *mut u16,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetTimeByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        time: *const u16,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcGetTimeByIndex: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        index: u32,
+        time: *mut u16,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetNumByIndex: unsafe extern "C" fn(
         handle: *mut RfcDataContainerHandle,
         index: u32,
         value: *const u16,
@@ -825,10 +2447,11 @@ pub struct RfcApi {
     ) -> RfcRc,
 
     #[allow(non_snake_case)]
-    RfcSetIntByIndex: unsafe extern "C" fn(
+    RfcGetNumByIndex: unsafe extern "C" fn(
         handle: *mut RfcDataContainerHandle,
         index: u32,
-        value: i64,
+        value: *mut u16,
+        length: u32,
         error: *mut RfcErrorInfo,
     ) -> RfcRc,
 
@@ -859,12 +2482,21 @@ pub struct RfcApi {
     RfcGetStringByIndex: unsafe extern "C" fn(
         fun: *const RfcDataContainerHandle,
         index: u32,
-        buf: *mut u8,
+        buf: *mut u16,
         len: u32,
         out_len: *mut u32,
         error: *mut RfcErrorInfo,
     ) -> RfcRc,
 
+    #[allow(non_snake_case)]
+    RfcSetStringByIndex: unsafe extern "C" fn(
+        fun: *const RfcDataContainerHandle,
+        index: u32,
+        value: *const u16,
+        len: u32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
     #[allow(non_snake_case)]
     RfcGetStringLengthByIndex: unsafe extern "C" fn(
         fun: *const RfcDataContainerHandle,
@@ -892,6 +2524,24 @@ pub struct RfcApi {
         error: *mut RfcErrorInfo,
     ) -> RfcRc,
 
+    #[allow(non_snake_case)]
+    RfcSetBytesByIndex: unsafe extern "C" fn(
+        fun: *const RfcDataContainerHandle,
+        index: u32,
+        value: *const u8,
+        len: u32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcGetBytesByIndex: unsafe extern "C" fn(
+        fun: *const RfcDataContainerHandle,
+        index: u32,
+        value: *mut u8,
+        buflen: u32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
     #[allow(non_snake_case)]
     RfcDescribeType: unsafe extern "C" fn(
         fun: *const RfcDataContainerHandle,
@@ -954,6 +2604,12 @@ pub struct RfcApi {
         error: *mut RfcErrorInfo,
     ) -> RfcRc,
 
+    #[allow(non_snake_case)]
+    RfcGetCurrentRow: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcDataContainerHandle,
+
     #[allow(non_snake_case)]
     RfcMoveTo: unsafe extern "C" fn(
         handle: *mut RfcDataContainerHandle,
@@ -976,5 +2632,401 @@ pub struct RfcApi {
     ) -> RfcRc,
 
     #[allow(non_snake_case)]
-    RfcCloseConnection: unsafe extern "C" fn (handle: *mut RfcConnectionHandle, error: *mut RfcErrorInfo) -> RfcRc
+    RfcInsertNewRow: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcDataContainerHandle,
+
+    #[allow(non_snake_case)]
+    RfcDeleteCurrentRow: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetParameterActive: unsafe extern "C" fn(
+        fun: *mut RfcDataContainerHandle,
+        name: *const u16,
+        is_active: i32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcDeleteAllRows: unsafe extern "C" fn(
+        handle: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcCloseConnection: unsafe extern "C" fn (handle: *mut RfcConnectionHandle, error: *mut RfcErrorInfo) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcPing: unsafe extern "C" fn (handle: *mut RfcConnectionHandle, error: *mut RfcErrorInfo) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcCancel: unsafe extern "C" fn (handle: *mut RfcConnectionHandle, error: *mut RfcErrorInfo) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcResetServerContext: unsafe extern "C" fn (handle: *mut RfcConnectionHandle, error: *mut RfcErrorInfo) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcGetTransactionID: unsafe extern "C" fn(
+        handle: *mut RfcConnectionHandle,
+        tid: *mut u16,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcCreateTransaction: unsafe extern "C" fn(
+        handle: *mut RfcConnectionHandle,
+        tid: *const u16,
+        queue_name: *const u16,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcTransactionHandle,
+
+    #[allow(non_snake_case)]
+    RfcInvokeInTransaction: unsafe extern "C" fn(
+        handle: *mut RfcTransactionHandle,
+        fun: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSubmitTransaction: unsafe extern "C" fn(
+        handle: *mut RfcTransactionHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcConfirmTransaction: unsafe extern "C" fn(
+        handle: *mut RfcTransactionHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcDestroyTransaction: unsafe extern "C" fn(
+        handle: *mut RfcTransactionHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcRegisterServer: unsafe extern "C" fn(
+        params: *const RfcConnectionParameter,
+        param_count: u32,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcConnectionHandle,
+
+    #[allow(non_snake_case)]
+    RfcListenAndDispatch: unsafe extern "C" fn(
+        handle: *mut RfcConnectionHandle,
+        timeout: i32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcInstallServerFunction: unsafe extern "C" fn(
+        sys_id: *const u16,
+        fd: *mut RfcFunctionDescHandle,
+        server_function: RfcServerFunction,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcInstallGenericServerFunction: unsafe extern "C" fn(
+        server_function: RfcServerFunction,
+        func_desc_provider: RfcFuncDescCallback,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcCreateFunctionDesc: unsafe extern "C" fn(
+        name: *const u16,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcFunctionDescHandle,
+
+    #[allow(non_snake_case)]
+    RfcAddParameter: unsafe extern "C" fn(
+        fd: *mut RfcFunctionDescHandle,
+        param_desc: *const RfcParameterDesc,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcCreateTypeDesc: unsafe extern "C" fn(
+        name: *const u16,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcDataContainerHandle,
+
+    #[allow(non_snake_case)]
+    RfcAddTypeField: unsafe extern "C" fn(
+        type_handle: *mut RfcDataContainerHandle,
+        field_desc: *const RfcFieldDesc,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcSetTypeLength: unsafe extern "C" fn(
+        type_handle: *mut RfcDataContainerHandle,
+        nuc_byte_length: u32,
+        uc_byte_length: u32,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcAddTypeDesc: unsafe extern "C" fn(
+        repository_id: *const u16,
+        type_handle: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcAddFunctionDesc: unsafe extern "C" fn(
+        repository_id: *const u16,
+        fd: *mut RfcFunctionDescHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcDestroyTypeDesc: unsafe extern "C" fn(
+        type_handle: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcDestroyFunctionDesc: unsafe extern "C" fn(
+        fd: *mut RfcFunctionDescHandle,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc,
+
+    #[allow(non_snake_case)]
+    RfcDescribeFunction: unsafe extern "C" fn(
+        fun: *mut RfcDataContainerHandle,
+        error: *mut RfcErrorInfo,
+    ) -> *mut RfcFunctionDescHandle,
+
+    #[allow(non_snake_case)]
+    RfcGetFunctionName: unsafe extern "C" fn(
+        fd: *mut RfcFunctionDescHandle,
+        name: *mut u16,
+        error: *mut RfcErrorInfo,
+    ) -> RfcRc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{ParameterSpec, RfcRepository};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Run `f` on a CHANGING parameter VALUE of a function created from a
+    /// repository. Needs libsapnwrfc in the search path of the dynamic
+    /// loader, but no SAP system.
+    fn with_parameter<F>(field_type: RfcType, nuc_length: u32, uc_length: u32, f: F)
+    where
+        F: FnOnce(&mut RfcParameter),
+    {
+        // Tests run in parallel, so every one gets its own function
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("Z_RSRFC_TEST_{}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let lib = crate::RfcLib::new().expect("libsapnwrfc not found");
+        let mut repository = RfcRepository::new(&lib, "RSRFC_TEST").unwrap();
+        let value = ParameterSpec {
+            name: "VALUE",
+            field_type,
+            direction: RfcDirection::RfcChanging,
+            nuc_length,
+            uc_length,
+            decimals: 0,
+            optional: false,
+            type_name: None,
+        };
+        repository.add_function(&name, &[value]).unwrap();
+        let mut fun = repository.get_function(&name).unwrap();
+        f(fun.get_mut_parameter("VALUE").unwrap());
+    }
+
+    #[test]
+    fn encode_value_counts_utf16_code_units() {
+        let (v, len) = encode_value("Müller", "NAME").unwrap();
+        assert_eq!("Müller".len(), 7);
+        assert_eq!(len, 6);
+        assert_eq!(v.len(), 7);
+        assert_eq!(v[6], 0);
+
+        // Outside the BMP, one char is two code units
+        let (_, len) = encode_value("\u{1F600}", "NAME").unwrap();
+        assert_eq!(len, 2);
+
+        let (v, len) = encode_value("", "NAME").unwrap();
+        assert_eq!((v, len), (vec![0], 0));
+
+        assert!(encode_value("a\0b", "NAME").is_err());
+    }
+
+    /// What RfcGetStringByIndex does with a buffer: copy the value and a
+    /// NUL, and return the length of the value
+    fn fill_string_buffer(value: &str) -> Vec<u16> {
+        let units: Vec<u16> = value.encode_utf16().collect();
+        let mut buf = string_buffer(units.len() as u32);
+        assert_eq!(buf.len(), units.len() + 1);
+        buf[..units.len()].copy_from_slice(&units);
+        take_string(buf, units.len() as u32)
+    }
+
+    #[test]
+    fn string_buffer_keeps_the_last_char() {
+        let forty = "0123456789".repeat(4);
+        for value in ["", "A", "Müller", forty.as_str()] {
+            let units = fill_string_buffer(value);
+            assert_eq!(String::from_utf16(&units).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn rfc_type_raw_round_trip() {
+        for code in 0..64 {
+            assert_eq!(RfcType::from_raw(code).to_raw(), code);
+        }
+        assert_eq!(RfcType::from_raw(29), RfcType::String);
+        assert_eq!(RfcType::from_raw(11), RfcType::Unknown(11));
+        assert_eq!(RfcType::Unknown(99).to_string(), "UNKNOWN(99)");
+    }
+
+    #[test]
+    fn check_char_width_boundaries() {
+        // CHAR10 is 20 bytes in the unicode layout
+        assert!(check_char_width(RfcType::Char, 20, 0, "F").is_ok());
+        assert!(check_char_width(RfcType::Char, 20, 10, "F").is_ok());
+        let err = check_char_width(RfcType::Char, 20, 11, "F").unwrap_err();
+        assert_eq!(err.message(), "Value of 11 chars exceeds the field width of 10 chars of F");
+        assert!(check_char_width(RfcType::String, 8, 1000, "F").is_ok());
+    }
+
+    #[test]
+    fn value_end_keeps_nuls_within_the_value() {
+        let buf: Vec<u16> = "AB\0\0CD  \0\0".encode_utf16().collect();
+        assert_eq!(String::from_utf16(&buf[..value_end(&buf)]).unwrap(), "AB\0\0CD  ");
+        assert_eq!(value_end(&[0, 0, 0]), 0);
+        assert_eq!(value_end(&[]), 0);
+        assert_eq!(value_end(&[65, 66]), 2);
+
+        // A value filling the field, in a buffer of the field width plus one
+        let mut buf: Vec<u16> = "ABCDEFGHIJ".encode_utf16().collect();
+        buf.push(0);
+        assert_eq!(String::from_utf16(&buf[..value_end(&buf)]).unwrap(), "ABCDEFGHIJ");
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn string_round_trip() {
+        let forty = "0123456789".repeat(4);
+        let one_kb = "0123456789abcdef".repeat(64);
+        for value in ["", "A", forty.as_str(), one_kb.as_str()] {
+            with_parameter(RfcType::String, 8, 8, |p| {
+                p.set_string(value).unwrap();
+                assert_eq!(p.get_string().unwrap(), value);
+            });
+        }
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn chars_round_trip() {
+        with_parameter(RfcType::Char, 10, 20, |p| {
+            p.set_string("Müller").unwrap();
+            assert_eq!(p.get_chars_trimmed().unwrap(), "Müller");
+        });
+        // A value of the full field width comes back unchanged
+        with_parameter(RfcType::Char, 10, 20, |p| {
+            p.set_string("MüllerABCD").unwrap();
+            assert_eq!(p.get_chars().unwrap(), "MüllerABCD");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn int_round_trip() {
+        let cases = [
+            (RfcType::Int, 4, -2_000_000_000),
+            (RfcType::Int2, 2, -300),
+            (RfcType::Int1, 1, 255),
+        ];
+        for (field_type, len, value) in cases {
+            with_parameter(field_type, len, len, |p| {
+                p.set_int(value).unwrap();
+                assert_eq!(p.get_int().unwrap(), value);
+            });
+        }
+        with_parameter(RfcType::Int8, 8, 8, |p| {
+            let err = p.get_int().unwrap_err();
+            assert_eq!(err.message(), "Parameter is of type INT8; use get_int8 instead of get_int");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs libsapnwrfc"]
+    fn float_round_trip() {
+        with_parameter(RfcType::Float, 8, 8, |p| {
+            p.set_float(-1234.5625).unwrap();
+            assert_eq!(p.get_float().unwrap(), -1234.5625);
+        });
+    }
+
+    fn decoded_field(name: &str, index: u32) -> RfcDecodedField<'static, 'static> {
+        RfcDecodedField {
+            name: name.to_string(),
+            index,
+            len: 2,
+            field_type: RfcType::Char,
+            sub_fields: None,
+            phantom: PhantomData,
+        }
+    }
+
+    #[test]
+    fn field_position_ignores_case() {
+        let fields = vec![decoded_field("MANDT", 0), decoded_field("BNAME", 1)];
+        assert_eq!(field_position(&fields, "bname"), Some(1));
+        assert_eq!(field_position(&fields, "Mandt"), Some(0));
+        assert_eq!(field_position(&fields, "BNAM"), None);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn decimal_round_trip() {
+        let value = Decimal::from_str("-1234.56").unwrap();
+        // set_decimal passes to_string to the RFC library
+        assert_eq!(value.to_string(), "-1234.56");
+        assert_eq!(parse_abap_decimal("1234.56-").unwrap(), value);
+        assert_eq!(parse_abap_decimal(" 1234.56- ").unwrap(), value);
+        assert_eq!(parse_abap_decimal("-1234.56").unwrap(), value);
+        assert_eq!(parse_abap_decimal("1.5E+3").unwrap(), Decimal::from(1500));
+        assert!(parse_abap_decimal("12a").is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn utc_long_year_boundaries() {
+        assert!(check_utc_long_year(1).is_ok());
+        assert!(check_utc_long_year(9999).is_ok());
+        for year in [0, -1, 10000] {
+            assert_eq!(check_utc_long_year(year).unwrap_err().code, RfcRc::RfcInvalidParameter);
+        }
+    }
+
+    #[test]
+    fn int_range_int1_boundaries() {
+        let (min, max) = int_range(RfcType::Int1);
+        let range = min..=max;
+        assert!(range.contains(&0));
+        assert!(range.contains(&255));
+        assert!(!range.contains(&256));
+        assert!(!range.contains(&-1));
+    }
+
+    #[test]
+    fn int_range_int2_and_int() {
+        assert_eq!(int_range(RfcType::Int2), (-32768, 32767));
+        assert_eq!(int_range(RfcType::Int), (i64::from(i32::MIN), i64::from(i32::MAX)));
+    }
 }
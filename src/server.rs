@@ -0,0 +1,311 @@
+//! RFC server: registers at an SAP gateway, so ABAP programs can call
+//! function modules implemented in Rust with CALL FUNCTION ... DESTINATION.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+use widestring::U16CStr;
+
+use crate::connparams::RfcConnParmHelper;
+use crate::error::*;
+use crate::rfc::*;
+use crate::{parameter_descs, RfcConnection};
+
+/// Handler for one function module, see RfcServer::add_handler
+pub type ServerHandler<'conn> = Box<dyn FnMut(&mut ServerFunction) -> Result<(), RfcErrorInfo> + 'conn>;
+
+/// Something that can handle an incoming call, i.e. an RfcServer of any
+/// lifetime
+trait Dispatch {
+    fn dispatch(&mut self, fun: *mut RfcDataContainerHandle) -> Result<(), RfcErrorInfo>;
+    fn describe(&self, name: &str) -> Result<FunctionDesc, RfcErrorInfo>;
+}
+
+thread_local! {
+    // The server dispatching on this thread. The RFC library calls the
+    // installed functions from within RfcListenAndDispatch on the same
+    // thread, without any user data, so this is how they find their handlers.
+    static DISPATCHER: Cell<Option<*mut (dyn Dispatch + 'static)>> = Cell::new(None);
+}
+
+/// A server connection registered at an SAP gateway. Create it with the
+/// registration parameters (program_id, gwhost, gwserv), add a handler
+/// for every function module and then call serve.
+///
+/// The function descriptions are looked up with the `repository`
+/// connection, which is usually a client connection to the calling system.
+pub struct RfcServer<'conn> {
+    handle: *mut RfcConnectionHandle,
+    repository: &'conn RfcConnection<'conn>,
+    handlers: HashMap<String, ServerHandler<'conn>>,
+    /// Handler for functions without a handler of their own, see on_any
+    fallback: Option<ServerHandler<'conn>>,
+}
+
+/// A call received by an RfcServer. The directions of the parameters are
+/// those of the server: IMPORTING parameters can be read and EXPORTING
+/// parameters written.
+pub struct ServerFunction<'conn> {
+    name: String,
+    parameters: Vec<RfcParameter<'conn, 'conn>>,
+}
+
+impl<'conn> RfcServer<'conn> {
+    /// Register at the gateway given by `parms`, e.g. built with the
+    /// program_id, gwhost and gwserv methods of ConnectionBuilder
+    pub fn register(
+        repository: &'conn RfcConnection<'conn>,
+        parms: &RfcConnParmHelper,
+    ) -> Result<RfcServer<'conn>, RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let handle = unsafe {
+            parms.as_vec(|pv| repository.rfc_lib.rfc_api.RfcRegisterServer(pv.as_ptr(), pv.len() as u32, &mut err_trunk))
+        };
+        if handle.is_null() {
+            return Err(err_trunk);
+        }
+        Ok(RfcServer {
+            handle,
+            repository,
+            handlers: HashMap::new(),
+            fallback: None,
+        })
+    }
+
+    /// Handle calls of the function module `name` with a closure:
+    ///
+    /// ```no_run
+    /// # use rsrfc::server::RfcServer;
+    /// # fn example(server: &mut RfcServer) -> Result<(), rsrfc::error::RfcErrorInfo> {
+    /// server.on("Z_ECHO", |func| {
+    ///     let text = func.get_parameter("TEXT").unwrap().get_string()?;
+    ///     func.get_mut_parameter("ECHO").unwrap().set_string(&text)
+    /// })
+    /// # }
+    /// ```
+    ///
+    /// Return an error made with RfcErrorInfo::abap_exception to raise an
+    /// exception of the function module. Any other error is reported to the
+    /// caller as a SYSTEM_FAILURE with its message.
+    pub fn on<F>(&mut self, name: &str, handler: F) -> Result<(), RfcErrorInfo>
+    where
+        F: FnMut(&mut ServerFunction) -> Result<(), RfcErrorInfo> + 'conn,
+    {
+        self.add_handler(name, Box::new(handler))
+    }
+
+    /// Handle calls of every function module that has no handler of its
+    /// own, see on. The function descriptions are looked up with the
+    /// repository connection when a call comes in.
+    pub fn on_any<F>(&mut self, handler: F) -> Result<(), RfcErrorInfo>
+    where
+        F: FnMut(&mut ServerFunction) -> Result<(), RfcErrorInfo> + 'conn,
+    {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.repository.rfc_lib.rfc_api.RfcInstallGenericServerFunction(
+                server_function,
+                function_desc_provider,
+                &mut err_trunk,
+            )
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.fallback = Some(Box::new(handler));
+        Ok(())
+    }
+
+    /// Handle calls of the function module `name`, see on
+    pub fn add_handler(&mut self, name: &str, handler: ServerHandler<'conn>) -> Result<(), RfcErrorInfo> {
+        let desc = self.repository.function_desc(name)?;
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.repository.rfc_lib.rfc_api.RfcInstallServerFunction(
+                std::ptr::null(),
+                desc.as_ptr(),
+                server_function,
+                &mut err_trunk,
+            )
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        self.handlers.insert(name.to_uppercase(), handler);
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for a call and dispatch it to its handler.
+    /// Returns RfcRetry if no call came in, and RfcOk or the error code
+    /// reported to the caller after a call. Errors leave the server
+    /// connection unusable.
+    pub fn listen_and_dispatch(&mut self, timeout: Duration) -> Result<RfcRc, RfcErrorInfo> {
+        let timeout = timeout.as_secs().min(i32::MAX as u64) as i32;
+        let handle = self.handle;
+        let rfc_lib = self.repository.rfc_lib;
+        let dispatcher: *mut (dyn Dispatch + 'conn) = self;
+        // SAFETY: the pointer is only used by server_function while this
+        // function runs, and removed again before it returns
+        let dispatcher: *mut (dyn Dispatch + 'static) = unsafe { std::mem::transmute(dispatcher) };
+        let previous = DISPATCHER.with(|d| d.replace(Some(dispatcher)));
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { rfc_lib.rfc_api.RfcListenAndDispatch(handle, timeout, &mut err_trunk) };
+        DISPATCHER.with(|d| d.set(previous));
+        match res {
+            RfcRc::RfcOk
+            | RfcRc::RfcRetry
+            | RfcRc::RfcAbapException
+            | RfcRc::RfcAbapMessage
+            | RfcRc::RfcExternalFailure => Ok(res),
+            _ => Err(err_trunk),
+        }
+    }
+
+    /// Dispatch calls until the server connection fails, e.g. because the
+    /// gateway closed it
+    pub fn serve(&mut self) -> Result<(), RfcErrorInfo> {
+        loop {
+            self.listen_and_dispatch(Duration::from_secs(60))?;
+        }
+    }
+}
+
+impl<'conn> Dispatch for RfcServer<'conn> {
+    fn dispatch(&mut self, fun: *mut RfcDataContainerHandle) -> Result<(), RfcErrorInfo> {
+        let rfc_lib = self.repository.rfc_lib;
+        let mut err_trunk = RfcErrorInfo::new();
+        let fd = unsafe { rfc_lib.rfc_api.RfcDescribeFunction(fun, &mut err_trunk) };
+        if fd.is_null() {
+            return Err(err_trunk);
+        }
+        let mut name = [0u16; 31];
+        let res = unsafe { rfc_lib.rfc_api.RfcGetFunctionName(fd, name.as_mut_ptr(), &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        let name = decode_buf(&name);
+        let handler = match self.handlers.get_mut(&name) {
+            Some(handler) => handler,
+            None => self
+                .fallback
+                .as_mut()
+                .ok_or_else(|| RfcErrorInfo::custom(&format!("No handler for function {}", name)))?,
+        };
+
+        let descs = parameter_descs(rfc_lib, fd)?;
+        let mut parameters = Vec::with_capacity(descs.len());
+        for (i, rpd) in descs.iter().enumerate() {
+            let mut p = rpd.to_parameter(&rfc_lib.rfc_api, i as u32, fun)?;
+            p.direction = server_direction(p.direction);
+            parameters.push(p);
+        }
+        handler(&mut ServerFunction { name, parameters })
+    }
+
+    fn describe(&self, name: &str) -> Result<FunctionDesc, RfcErrorInfo> {
+        self.repository.function_desc(name)
+    }
+}
+
+impl<'conn> Drop for RfcServer<'conn> {
+    fn drop(&mut self) {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.repository.rfc_lib.rfc_api.RfcCloseConnection(self.handle, &mut err_trunk) };
+        if !res.is_ok() {
+            eprintln!(
+                "Warning: Unable to close RFC server connection: {}",
+                String::from_utf16_lossy(&err_trunk.message)
+            );
+        }
+    }
+}
+
+impl<'conn> ServerFunction<'conn> {
+    /// Name of the called function module
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// All parameters of the function, in declaration order
+    pub fn parameters(&self) -> &[RfcParameter<'conn, 'conn>] {
+        &self.parameters
+    }
+
+    /// Get a reference to a parameter by name, case insensitively
+    pub fn get_parameter(&self, parameter_name: &str) -> Option<&RfcParameter<'conn, 'conn>> {
+        self.parameters.iter().find(|p| p.name.eq_ignore_ascii_case(parameter_name))
+    }
+
+    /// Get a mutable reference to a parameter by name, case insensitively
+    pub fn get_mut_parameter(&mut self, parameter_name: &str) -> Option<&mut RfcParameter<'conn, 'conn>> {
+        self.parameters.iter_mut().find(|p| p.name.eq_ignore_ascii_case(parameter_name))
+    }
+}
+
+/// The server reads what the caller exports to it and vice versa
+fn server_direction(direction: RfcDirection) -> RfcDirection {
+    match direction {
+        RfcDirection::RfcImport => RfcDirection::RfcExport,
+        RfcDirection::RfcExport => RfcDirection::RfcImport,
+        other => other,
+    }
+}
+
+/// Installed for every function of an RfcServer; forwards the call to the
+/// server dispatching on this thread
+unsafe extern "C" fn server_function(
+    _conn: *mut RfcConnectionHandle,
+    fun: *mut RfcDataContainerHandle,
+    error: *mut RfcErrorInfo,
+) -> RfcRc {
+    let dispatcher = match DISPATCHER.with(|d| d.get()) {
+        Some(d) => d,
+        None => {
+            let mut err = RfcErrorInfo::custom("Call received outside of RfcServer::listen_and_dispatch");
+            err.code = RfcRc::RfcExternalFailure;
+            err.group = RfcErrorGroup::ExternalRuntimeFailure;
+            *error = err;
+            return RfcRc::RfcExternalFailure;
+        }
+    };
+    // Unwinding into the RFC library is undefined behaviour
+    let res = match catch_unwind(AssertUnwindSafe(|| (*dispatcher).dispatch(fun).err())) {
+        Ok(res) => res,
+        Err(_) => Some(RfcErrorInfo::custom("Server function handler panicked")),
+    };
+    match res {
+        None => RfcRc::RfcOk,
+        Some(mut err) => {
+            if err.code == RfcRc::RfcCaiberp {
+                err.code = RfcRc::RfcExternalFailure;
+                err.group = RfcErrorGroup::ExternalRuntimeFailure;
+            }
+            let code = err.code;
+            *error = err;
+            code
+        }
+    }
+}
+
+/// Installed by RfcServer::on_any; returns the description of a function
+/// for which no server function was installed
+unsafe extern "C" fn function_desc_provider(
+    function_name: *const u16,
+    _attributes: RfcAttributes,
+    fd: *mut *mut RfcFunctionDescHandle,
+) -> RfcRc {
+    let dispatcher = match DISPATCHER.with(|d| d.get()) {
+        Some(d) => d,
+        None => return RfcRc::RfcNotFound,
+    };
+    let name = U16CStr::from_ptr_str(function_name).to_string_lossy();
+    match catch_unwind(AssertUnwindSafe(|| (*dispatcher).describe(&name).ok())) {
+        Ok(Some(handle)) => {
+            *fd = handle.as_ptr();
+            RfcRc::RfcOk
+        }
+        _ => RfcRc::RfcNotFound,
+    }
+}
@@ -0,0 +1,125 @@
+//! Transactional RFC (tRFC). The functions of a transaction are recorded
+//! under a transaction ID (TID) and executed exactly once by the SAP
+//! system, even if the transaction is submitted again after an error.
+
+use std::ptr::null;
+
+use widestring::U16CString;
+
+use crate::error::*;
+use crate::rfc::*;
+use crate::{RfcConnection, RfcFunction, RfcLib};
+
+/// Length of a transaction ID in characters
+const TID_LEN: usize = 24;
+
+/// A transaction holding one or more function calls, see
+/// RfcConnection::create_transaction. Nothing is sent to the SAP system
+/// before submit.
+pub struct Transaction<'conn> {
+    handle: *mut RfcTransactionHandle,
+    tid: String,
+    rfc_lib: &'conn RfcLib,
+}
+
+impl<'rfclib> RfcConnection<'rfclib> {
+    /// Create a transaction with a new TID and add the functions to it, in
+    /// order. Store the TID before submitting the transaction, so it can
+    /// be submitted again with create_transaction_with_tid after an error.
+    pub fn create_transaction(&self, funcs: &mut [RfcFunction]) -> Result<Transaction<'_>, RfcErrorInfo> {
+        let mut tid = [0u16; TID_LEN + 1];
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe {
+            self.rfc_lib.rfc_api.RfcGetTransactionID(self.connection_handle, tid.as_mut_ptr(), &mut err_trunk)
+        };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        let tid = String::from_utf16_lossy(&tid[..TID_LEN]);
+        self.create_transaction_with_tid(&tid, funcs)
+    }
+
+    /// Create a transaction with a known TID, e.g. to submit a transaction
+    /// again that failed before. The SAP system executes it only once.
+    pub fn create_transaction_with_tid(
+        &self,
+        tid: &str,
+        funcs: &mut [RfcFunction],
+    ) -> Result<Transaction<'_>, RfcErrorInfo> {
+        if tid.chars().count() != TID_LEN {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Invalid TID {}: a TID has {} characters",
+                tid, TID_LEN
+            )));
+        }
+        if let Some(f) = funcs.iter().find(|f| f.connection.is_none_or(|c| c.connection_handle != self.connection_handle)) {
+            return Err(RfcErrorInfo::custom(&format!(
+                "Function {} was created on a different connection",
+                f.name
+            )));
+        }
+        let tid_c = U16CString::from_str(tid).map_err(|e| interior_nul("TID", e))?;
+        let mut err_trunk = RfcErrorInfo::new();
+        let handle = unsafe {
+            self.rfc_lib.rfc_api.RfcCreateTransaction(self.connection_handle, tid_c.as_ptr(), null(), &mut err_trunk)
+        };
+        if handle.is_null() {
+            return Err(err_trunk);
+        }
+        let transaction = Transaction {
+            handle,
+            tid: tid.to_string(),
+            rfc_lib: self.rfc_lib,
+        };
+        for f in funcs.iter_mut() {
+            let res = unsafe { self.rfc_lib.rfc_api.RfcInvokeInTransaction(handle, f.fun, &mut err_trunk) };
+            if !res.is_ok() {
+                return Err(err_trunk);
+            }
+        }
+        Ok(transaction)
+    }
+}
+
+impl<'conn> Transaction<'conn> {
+    /// The transaction ID
+    pub fn tid(&self) -> &str {
+        &self.tid
+    }
+
+    /// Send the functions to the SAP system, which executes them. If this
+    /// fails, the transaction can be submitted again under the same TID.
+    pub fn submit(&self) -> Result<(), RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcSubmitTransaction(self.handle, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(())
+    }
+
+    /// Tell the SAP system that the transaction was processed, so it can
+    /// forget the TID. Call this after submit succeeded and the TID was
+    /// removed from the caller's own store.
+    pub fn confirm(self) -> Result<(), RfcErrorInfo> {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcConfirmTransaction(self.handle, &mut err_trunk) };
+        if !res.is_ok() {
+            return Err(err_trunk);
+        }
+        Ok(())
+    }
+}
+
+impl<'conn> Drop for Transaction<'conn> {
+    fn drop(&mut self) {
+        let mut err_trunk = RfcErrorInfo::new();
+        let res = unsafe { self.rfc_lib.rfc_api.RfcDestroyTransaction(self.handle, &mut err_trunk) };
+        if !res.is_ok() {
+            eprintln!(
+                "Warning: Unable to destroy RFC transaction: {}",
+                String::from_utf16_lossy(&err_trunk.message)
+            );
+        }
+    }
+}